@@ -0,0 +1,573 @@
+//! Asynchronous logging through a background thread
+//!
+//! See [`Async`].
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{fmt, result};
+use std::sync::{mpsc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use crate::{
+    Drain, FlushError, Level, Never, OwnedKVList, OwnedRecord, Record, StdError,
+};
+
+/// What `Async` does with a record when its queue is full
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OverflowStrategy {
+    /// Block the logging thread until there is room in the queue
+    Block,
+    /// Drop the record being logged
+    DropNewest,
+    /// Drop the oldest queued record to make room for the one being logged
+    DropOldest,
+    /// Drop the record being logged if it is less severe than the given
+    /// level, and block otherwise
+    DropByLevel(Level),
+}
+
+/// Error returned by `Async`
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsyncError {
+    /// The worker thread has stopped, typically because the drain it was
+    /// logging to panicked
+    Disconnected,
+}
+
+impl fmt::Display for AsyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            AsyncError::Disconnected => {
+                f.write_str("slog::Async worker thread has stopped")
+            }
+        }
+    }
+}
+
+impl StdError for AsyncError {}
+
+enum Item {
+    Record(OwnedRecord),
+    Flush(mpsc::Sender<result::Result<(), FlushError>>),
+}
+
+struct State {
+    queue: VecDeque<Item>,
+    /// Number of `Item::Record`s in `queue`
+    records: usize,
+    /// `Async` was dropped; the worker should exit once the queue is empty
+    closed: bool,
+    /// The worker has exited
+    disconnected: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    chan_size: usize,
+    overflow_strategy: OverflowStrategy,
+    dropped: AtomicUsize,
+    /// Records dropped since the worker last reported it
+    unreported: AtomicUsize,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // The state is kept consistent across panics, so poisoning can be
+        // ignored
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        self.unreported.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Marks the worker as disconnected when it exits, even if by panic
+struct DisconnectOnDrop(Arc<Shared>);
+
+impl Drop for DisconnectOnDrop {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.disconnected = true;
+        // Dropping pending flush requests wakes up their callers
+        state.queue.clear();
+        state.records = 0;
+        drop(state);
+        self.0.not_full.notify_all();
+    }
+}
+
+fn worker<D>(shared: Arc<Shared>, drain: D)
+where
+    D: Drain<Ok = (), Err = Never>,
+{
+    let _guard = DisconnectOnDrop(shared.clone());
+    loop {
+        let item = {
+            let mut state = shared.lock();
+            loop {
+                if let Some(item) = state.queue.pop_front() {
+                    if let Item::Record(_) = item {
+                        state.records -= 1;
+                    }
+                    break item;
+                }
+                if state.closed {
+                    return;
+                }
+                state = shared
+                    .not_empty
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        };
+        shared.not_full.notify_one();
+
+        let unreported = shared.unreported.swap(0, Ordering::Relaxed);
+        if unreported > 0 {
+            let _ = drain.log(
+                &crate::record!(
+                    Level::Error,
+                    "",
+                    &format_args!(
+                        "slog::Async: dropped records due to queue overflow"
+                    ),
+                    crate::b!("count" => unreported)
+                ),
+                &OwnedKVList::from(crate::o!()),
+            );
+        }
+
+        match item {
+            Item::Record(record) => {
+                let _ = record.replay(&drain);
+            }
+            Item::Flush(reply) => {
+                let _ = reply.send(drain.flush());
+            }
+        }
+    }
+}
+
+/// Builder for `Async`
+///
+/// Created with `Async::new`.
+#[must_use = "does nothing unless built"]
+pub struct AsyncBuilder<D> {
+    drain: D,
+    chan_size: usize,
+    overflow_strategy: OverflowStrategy,
+    thread_name: Option<String>,
+}
+
+impl<D> AsyncBuilder<D>
+where
+    D: Drain<Ok = (), Err = Never> + Send + 'static,
+{
+    /// Set the number of records that can be queued (default: 128)
+    ///
+    /// # Panics
+    ///
+    /// If `chan_size` is zero.
+    pub fn chan_size(mut self, chan_size: usize) -> Self {
+        assert!(chan_size > 0, "slog::Async queue can't be empty");
+        self.chan_size = chan_size;
+        self
+    }
+
+    /// Set what to do when the queue is full (default:
+    /// `OverflowStrategy::DropNewest`)
+    pub fn overflow_strategy(
+        mut self,
+        overflow_strategy: OverflowStrategy,
+    ) -> Self {
+        self.overflow_strategy = overflow_strategy;
+        self
+    }
+
+    /// Set the name of the worker thread
+    pub fn thread_name(mut self, name: String) -> Self {
+        self.thread_name = Some(name);
+        self
+    }
+
+    /// Spawn the worker thread and build the `Async` drain
+    ///
+    /// # Panics
+    ///
+    /// If the worker thread can't be spawned.
+    pub fn build(self) -> Async {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::with_capacity(self.chan_size),
+                records: 0,
+                closed: false,
+                disconnected: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            chan_size: self.chan_size,
+            overflow_strategy: self.overflow_strategy,
+            dropped: AtomicUsize::new(0),
+            unreported: AtomicUsize::new(0),
+        });
+        let mut builder = thread::Builder::new();
+        if let Some(name) = self.thread_name {
+            builder = builder.name(name);
+        }
+        let worker_shared = shared.clone();
+        let drain = self.drain;
+        let join = builder
+            .spawn(move || worker(worker_shared, drain))
+            .expect("failed to spawn slog::Async worker thread");
+        Async {
+            shared,
+            join: Mutex::new(Some(join)),
+        }
+    }
+}
+
+/// `Drain` logging through a background thread
+///
+/// Every record is captured into an `OwnedRecord` and sent through a bounded
+/// queue to a worker thread, which replays it into the wrapped drain. This
+/// moves formatting and IO off the logging threads.
+///
+/// When the queue is full, the `OverflowStrategy` decides whether the record
+/// is dropped or the logging thread waits. Dropped records are counted (see
+/// `Async::dropped`), and the worker reports them with an error-level record
+/// once it catches up.
+///
+/// Dropping `Async` waits for the worker thread to log every queued record.
+///
+/// ```
+/// use slog::{o, Discard, Drain};
+///
+/// let drain = slog::Async::new(Discard.fuse())
+///     .chan_size(1024)
+///     .overflow_strategy(slog::OverflowStrategy::Block)
+///     .build()
+///     .fuse();
+/// let log = slog::Logger::root(drain, o!());
+/// slog::info!(log, "logged on another thread");
+/// ```
+pub struct Async {
+    shared: Arc<Shared>,
+    join: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl Async {
+    /// Create a builder for an `Async` wrapping `drain`
+    ///
+    /// `drain` will be moved to the worker thread, so it must be `Send`.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<D>(drain: D) -> AsyncBuilder<D>
+    where
+        D: Drain<Ok = (), Err = Never> + Send + 'static,
+    {
+        AsyncBuilder {
+            drain,
+            chan_size: 128,
+            overflow_strategy: OverflowStrategy::DropNewest,
+            thread_name: None,
+        }
+    }
+
+    /// Build an `Async` wrapping `drain` with the default settings
+    #[allow(clippy::should_implement_trait)]
+    pub fn default<D>(drain: D) -> Async
+    where
+        D: Drain<Ok = (), Err = Never> + Send + 'static,
+    {
+        Async::new(drain).build()
+    }
+
+    /// Total number of records dropped because the queue was full
+    pub fn dropped(&self) -> usize {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    fn wait_not_full<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, State>,
+    ) -> MutexGuard<'a, State> {
+        while state.records >= self.shared.chan_size && !state.disconnected {
+            state = self
+                .shared
+                .not_full
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        state
+    }
+}
+
+impl Drain for Async {
+    type Ok = ();
+    type Err = AsyncError;
+
+    fn log(
+        &self,
+        record: &Record<'_>,
+        values: &OwnedKVList,
+    ) -> result::Result<(), AsyncError> {
+        let owned = OwnedRecord::new(record, values);
+        let mut state = self.shared.lock();
+        if state.records >= self.shared.chan_size {
+            match self.shared.overflow_strategy {
+                OverflowStrategy::Block => {
+                    state = self.wait_not_full(state);
+                }
+                OverflowStrategy::DropNewest => {
+                    if !state.disconnected {
+                        self.shared.record_dropped();
+                        return Ok(());
+                    }
+                }
+                OverflowStrategy::DropOldest => {
+                    let oldest = state
+                        .queue
+                        .iter()
+                        .position(|item| matches!(item, Item::Record(_)));
+                    if let Some(index) = oldest {
+                        state.queue.remove(index);
+                        state.records -= 1;
+                        self.shared.record_dropped();
+                    }
+                }
+                OverflowStrategy::DropByLevel(level) => {
+                    if record.level().is_at_least(level) {
+                        state = self.wait_not_full(state);
+                    } else if !state.disconnected {
+                        self.shared.record_dropped();
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        if state.disconnected {
+            return Err(AsyncError::Disconnected);
+        }
+        state.queue.push_back(Item::Record(owned));
+        state.records += 1;
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Wait for the worker to log every record queued before this call, and
+    /// flush the wrapped drain.
+    ///
+    /// Records queued by other threads while waiting may or may not be
+    /// flushed.
+    fn flush(&self) -> result::Result<(), FlushError> {
+        let (reply, response) = mpsc::channel();
+        {
+            let mut state = self.shared.lock();
+            if state.disconnected {
+                return Err(FlushError::Custom(Box::new(
+                    AsyncError::Disconnected,
+                )));
+            }
+            // Flush requests don't count towards `chan_size`, so they never
+            // block behind a full queue
+            state.queue.push_back(Item::Flush(reply));
+        }
+        self.shared.not_empty.notify_one();
+        response.recv().unwrap_or_else(|_| {
+            Err(FlushError::Custom(Box::new(AsyncError::Disconnected)))
+        })
+    }
+}
+
+impl Drop for Async {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.not_empty.notify_all();
+        let join = self
+            .join
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        if let Some(join) = join {
+            let _ = join.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger};
+    use alloc::vec::Vec;
+    use std::sync::mpsc::{Receiver, Sender};
+
+    /// Drain recording messages, optionally blocking until released
+    #[derive(Clone)]
+    struct Slow {
+        logged: Arc<Mutex<Vec<String>>>,
+        entered: Option<Arc<Mutex<Sender<()>>>>,
+        release: Option<Arc<Mutex<Receiver<()>>>>,
+    }
+
+    impl Drain for Slow {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            if let Some(ref entered) = self.entered {
+                entered.lock().unwrap().send(()).unwrap();
+            }
+            if let Some(ref release) = self.release {
+                release.lock().unwrap().recv().unwrap();
+            }
+            self.logged.lock().unwrap().push(record.msg().to_string());
+            Ok(())
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            Ok(())
+        }
+    }
+
+    /// Build a `Slow` drain blocking until released, and its controls
+    fn blocking() -> (Slow, Receiver<()>, Sender<()>) {
+        let (entered_tx, entered_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel();
+        let drain = Slow {
+            logged: Arc::default(),
+            entered: Some(Arc::new(Mutex::new(entered_tx))),
+            release: Some(Arc::new(Mutex::new(release_rx))),
+        };
+        (drain, entered_rx, release_tx)
+    }
+
+    fn log_n(drain: &Async, range: core::ops::Range<u32>) {
+        for i in range {
+            drain
+                .log(
+                    &crate::record!(
+                        Level::Info,
+                        "",
+                        &format_args!("{}", i),
+                        crate::b!()
+                    ),
+                    &OwnedKVList::from(o!()),
+                )
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn flush_waits_for_queued_records() {
+        let drain = Slow {
+            logged: Arc::default(),
+            entered: None,
+            release: None,
+        };
+        let logged = drain.logged.clone();
+        let log = Logger::root(Async::default(drain).fuse(), o!());
+        for i in 0..100 {
+            crate::info!(log, "{}", i);
+        }
+        log.flush().unwrap();
+        let expected: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        assert_eq!(*logged.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn drop_newest_when_full() {
+        let (drain, entered, release) = blocking();
+        let logged = drain.logged.clone();
+        let async_drain = Async::new(drain).chan_size(2).build();
+        // The worker takes "0" off the queue and blocks on it
+        log_n(&async_drain, 0..1);
+        entered.recv().unwrap();
+        log_n(&async_drain, 1..6);
+        assert_eq!(async_drain.dropped(), 3);
+
+        for _ in 0..4 {
+            release.send(()).unwrap();
+        }
+        drop(async_drain);
+        assert_eq!(
+            *logged.lock().unwrap(),
+            [
+                "0",
+                "slog::Async: dropped records due to queue overflow",
+                "1",
+                "2"
+            ]
+        );
+    }
+
+    #[test]
+    fn drop_oldest_when_full() {
+        let (drain, entered, release) = blocking();
+        let logged = drain.logged.clone();
+        let async_drain = Async::new(drain)
+            .chan_size(2)
+            .overflow_strategy(OverflowStrategy::DropOldest)
+            .build();
+        log_n(&async_drain, 0..1);
+        entered.recv().unwrap();
+        log_n(&async_drain, 1..6);
+        assert_eq!(async_drain.dropped(), 3);
+
+        for _ in 0..4 {
+            release.send(()).unwrap();
+        }
+        drop(async_drain);
+        assert_eq!(
+            *logged.lock().unwrap(),
+            [
+                "0",
+                "slog::Async: dropped records due to queue overflow",
+                "4",
+                "5"
+            ]
+        );
+    }
+
+    #[test]
+    fn disconnected_after_worker_panics() {
+        struct Panics;
+        impl Drain for Panics {
+            type Ok = ();
+            type Err = Never;
+            fn log(
+                &self,
+                _: &Record<'_>,
+                _: &OwnedKVList,
+            ) -> result::Result<(), Never> {
+                panic!("drain failure")
+            }
+        }
+
+        let async_drain = Async::default(Panics);
+        log_n(&async_drain, 0..1);
+        assert!(async_drain.flush().is_err());
+        let err = async_drain
+            .log(
+                &crate::record!(
+                    Level::Info,
+                    "",
+                    &format_args!(""),
+                    crate::b!()
+                ),
+                &OwnedKVList::from(o!()),
+            )
+            .unwrap_err();
+        assert_eq!(err, AsyncError::Disconnected);
+    }
+}
@@ -0,0 +1,371 @@
+//! Containing panics of drains and values
+//!
+//! See [`CatchUnwind`].
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use core::any::Any;
+use core::panic::AssertUnwindSafe;
+use core::{fmt, result};
+use std::panic::catch_unwind;
+
+use crate::{
+    Drain, FlushError, Key, Level, OwnedKVList, Record, RecordStatic,
+    Serializer, StdError, KV,
+};
+
+/// A panic caught by `CatchUnwind`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicError {
+    message: String,
+}
+
+impl PanicError {
+    fn from_payload(payload: Box<dyn Any + Send>) -> Self {
+        let message = if let Some(s) = payload.downcast_ref::<&str>() {
+            (*s).to_owned()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "Box<dyn Any>".to_owned()
+        };
+        PanicError { message }
+    }
+
+    /// The panic message
+    ///
+    /// This is `"Box<dyn Any>"` if the panic payload was neither a `&str`
+    /// nor a `String`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for PanicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Drain panicked: {}", self.message)
+    }
+}
+
+impl StdError for PanicError {}
+
+/// Error returned by `CatchUnwind::log`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatchUnwindError<E> {
+    /// The wrapped drain, or a value it serialized, panicked
+    Panic(PanicError),
+    /// The wrapped drain returned an error
+    Drain(E),
+}
+
+impl<E: fmt::Display> fmt::Display for CatchUnwindError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatchUnwindError::Panic(e) => fmt::Display::fmt(e, f),
+            CatchUnwindError::Drain(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> StdError for CatchUnwindError<E> {}
+
+/// `Drain` catching panics of the drain it wraps
+///
+/// A panic in a drain, or in a `Value::serialize` called by it, would
+/// otherwise unwind through the logging statement. `CatchUnwind` returns
+/// it as a `CatchUnwindError::Panic` instead.
+///
+/// Optionally, after a panic, a fallback `Error` record is logged to the
+/// wrapped drain, from the callsite of the record that panicked, without
+/// the logger values and the panicking record's key-value pairs. Its `panic`
+/// key holds the panic message, and its `key` key the key of the value
+/// that panicked, if any could be found. To find it, the key-value pairs
+/// are serialized again, so values panicking outside of any `Serializer`
+/// method can't be told apart from the drain itself panicking.
+///
+/// Panics are still reported by the panic hook, which by default prints
+/// them to standard error.
+///
+/// ```
+/// use slog::{o, CatchUnwind, Discard, Drain};
+///
+/// let drain = CatchUnwind::new(Discard).fallback(true).ignore_res();
+/// let _log = slog::Logger::root(drain, o!());
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+#[derive(Debug, Clone)]
+pub struct CatchUnwind<D: Drain> {
+    drain: D,
+    fallback: bool,
+}
+
+impl<D: Drain> CatchUnwind<D> {
+    /// Create `CatchUnwind`, without fallback records
+    pub fn new(drain: D) -> Self {
+        CatchUnwind {
+            drain,
+            fallback: false,
+        }
+    }
+
+    /// Set whether to log a fallback record after a panic
+    pub fn fallback(mut self, fallback: bool) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    fn log_fallback(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+        panic: &PanicError,
+    ) {
+        let key = find_panicking_key(record, logger_values)
+            .map(|key| key.to_string());
+        let rs = RecordStatic {
+            location: record.location(),
+            level: Level::Error,
+            tag: record.tag(),
+        };
+        // The fallback record may well panic too
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            self.drain.log(
+                &Record::new(
+                    &rs,
+                    &format_args!("slog::CatchUnwind: drain panicked"),
+                    crate::b!("panic" => panic.message(), "key" => &key),
+                ),
+                &OwnedKVList::from(crate::o!()),
+            )
+        }));
+    }
+}
+
+impl<D: Drain> Drain for CatchUnwind<D> {
+    type Ok = D::Ok;
+    type Err = CatchUnwindError<D::Err>;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        match catch_unwind(AssertUnwindSafe(|| {
+            self.drain.log(record, logger_values)
+        })) {
+            Ok(res) => res.map_err(CatchUnwindError::Drain),
+            Err(payload) => {
+                let panic = PanicError::from_payload(payload);
+                if self.fallback {
+                    self.log_fallback(record, logger_values, &panic);
+                }
+                Err(CatchUnwindError::Panic(panic))
+            }
+        }
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    /// Flush the wrapped drain
+    ///
+    /// A panic is returned as a `PanicError` in `FlushError::Custom`.
+    fn flush(&self) -> result::Result<(), FlushError> {
+        catch_unwind(AssertUnwindSafe(|| self.drain.flush())).unwrap_or_else(
+            |payload| {
+                Err(FlushError::Custom(Box::new(PanicError::from_payload(
+                    payload,
+                ))))
+            },
+        )
+    }
+}
+
+/// Serialize the key-value pairs of a record and its logger, and return the
+/// key of the value that panics, if any
+fn find_panicking_key(
+    record: &Record<'_>,
+    logger_values: &OwnedKVList,
+) -> Option<Key> {
+    let mut probe = Probe {
+        key: None,
+        nested: false,
+    };
+    let panicked = catch_unwind(AssertUnwindSafe(|| {
+        let _ = record.kv().serialize(record, &mut probe);
+        let _ = logger_values.serialize(record, &mut probe);
+    }))
+    .is_err();
+    if panicked {
+        probe.key
+    } else {
+        None
+    }
+}
+
+/// Serializer tracking the key being serialized
+struct Probe {
+    /// Set while in a `Serializer` method
+    key: Option<Key>,
+    /// In `emit_serde`, which emits nested values with its key
+    nested: bool,
+}
+
+struct Sink;
+
+impl fmt::Write for Sink {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl Serializer for Probe {
+    fn emit_arguments(
+        &mut self,
+        key: Key,
+        val: &fmt::Arguments<'_>,
+    ) -> crate::Result {
+        self.key = Some(key);
+        // Formatting is where `Display` implementations panic
+        let _ = fmt::write(&mut Sink, *val);
+        if !self.nested {
+            self.key = None;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "nested-values")]
+    fn emit_serde(
+        &mut self,
+        key: Key,
+        value: &dyn crate::SerdeValue,
+    ) -> crate::Result {
+        // The key is set by the first nested value
+        self.nested = true;
+        let res =
+            value.serialize_fallback(key, &mut crate::SerializerForward(self));
+        self.nested = false;
+        self.key = None;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+    use std::sync::Mutex;
+
+    /// Collects messages and formatted key-value pairs
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            values: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            let mut line = record.msg().to_string();
+            let mut ser = crate::AsFmtSerializer(|key, val| {
+                line.push_str(&alloc::format!(" {}={}", key, val));
+                Ok(())
+            });
+            record.kv().serialize(record, &mut ser).unwrap();
+            values.serialize(record, &mut ser).unwrap();
+            self.0.lock().unwrap().push(line);
+            Ok(())
+        }
+    }
+
+    /// Panics when formatted
+    struct Bomb;
+
+    impl fmt::Display for Bomb {
+        fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+            panic!("boom")
+        }
+    }
+
+    impl crate::Value for Bomb {
+        fn serialize(
+            &self,
+            _: &Record<'_>,
+            key: Key,
+            serializer: &mut dyn Serializer,
+        ) -> crate::Result {
+            serializer.emit_arguments(key, &format_args!("{}", self))
+        }
+    }
+
+    #[test]
+    fn catches_panicking_values() {
+        let collect = Collect::default();
+        let log = Logger::root(
+            CatchUnwind::new(collect.clone())
+                .fallback(true)
+                .ignore_res(),
+            o!("ok" => 1),
+        );
+        crate::info!(log, "fine"; "a" => 1);
+        crate::info!(log, "panics"; "a" => 1, "bomb" => Bomb);
+        let log = log.new(o!("bomb" => Bomb));
+        crate::info!(log, "panics");
+        assert_eq!(
+            *collect.0.lock().unwrap(),
+            [
+                "fine a=1 ok=1",
+                "slog::CatchUnwind: drain panicked key=bomb panic=boom",
+                "slog::CatchUnwind: drain panicked key=bomb panic=boom",
+            ]
+        );
+    }
+
+    #[test]
+    fn returns_panic_error() {
+        struct Panics;
+        impl Drain for Panics {
+            type Ok = ();
+            type Err = Never;
+            fn log(
+                &self,
+                record: &Record<'_>,
+                _: &OwnedKVList,
+            ) -> result::Result<(), Never> {
+                panic!("drain failed on {}", record.msg())
+            }
+            fn flush(&self) -> result::Result<(), FlushError> {
+                panic!("flush failed")
+            }
+        }
+
+        let drain = CatchUnwind::new(Panics);
+        let err = drain
+            .log(
+                &crate::record!(
+                    Level::Info,
+                    "",
+                    &format_args!("x"),
+                    crate::b!()
+                ),
+                &OwnedKVList::from(o!()),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err,
+            CatchUnwindError::Panic(PanicError {
+                message: "drain failed on x".to_owned()
+            })
+        );
+        match drain.flush() {
+            Err(FlushError::Custom(e)) => {
+                assert_eq!(e.to_string(), "Drain panicked: flush failed")
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
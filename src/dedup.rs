@@ -0,0 +1,248 @@
+//! Collapsing runs of identical records
+//!
+//! See [`Dedup`].
+
+use core::result;
+use std::sync::{Mutex, PoisonError};
+
+use crate::rate_limit::callsite;
+use crate::{
+    Drain, FlushError, Level, OwnedKVList, OwnedRecord, Record, RecordStatic,
+};
+
+/// Check if two records are repeats of each other
+fn is_repeat(a: &OwnedRecord, b: &OwnedRecord) -> bool {
+    a.level() == b.level()
+        && callsite(a.location()) == callsite(b.location())
+        && a.module() == b.module()
+        && a.tag() == b.tag()
+        && a.msg() == b.msg()
+        && a.kv() == b.kv()
+        && a.values() == b.values()
+}
+
+struct Run {
+    first: OwnedRecord,
+    repeats: u64,
+}
+
+/// `Drain` collapsing runs of identical records
+///
+/// Each record is compared with the previous one: its level, location, tag,
+/// message and key-value pairs (both the record's and the logger's). Records
+/// identical to the previous one are not logged. Instead, when the run of
+/// identical records ends, or on `Drain::flush`, a follow-up record is
+/// logged, with the same location, level and tag and a `repeated` key
+/// holding the number of repeats.
+///
+/// Values are compared as captured by `OwnedRecord`, so records with serde
+/// values (`nested-values` feature) are never considered identical.
+///
+/// Repeats that were not reported yet are lost when `Dedup` is dropped
+/// without being flushed.
+///
+/// ```
+/// use slog::{o, Dedup, Discard, Drain};
+///
+/// let drain = Dedup::new(Discard).fuse();
+/// let log = slog::Logger::root(drain, o!());
+/// for _ in 0..1000 {
+///     // Logged once, then "last message repeated 999 times"
+///     slog::error!(log, "connection refused"; "port" => 8080);
+/// }
+/// slog::info!(log, "giving up");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Dedup<D: Drain> {
+    drain: D,
+    run: Mutex<Option<Run>>,
+}
+
+impl<D: Drain> Dedup<D> {
+    /// Create `Dedup`
+    pub fn new(drain: D) -> Self {
+        Dedup {
+            drain,
+            run: Mutex::new(None),
+        }
+    }
+
+    /// Log the follow-up record for a run, if it had any repeats
+    fn report(&self, run: &mut Run) -> result::Result<(), D::Err> {
+        let repeats = core::mem::replace(&mut run.repeats, 0);
+        if repeats == 0 {
+            return Ok(());
+        }
+        run.first.with_record(|record, values| {
+            let rs = RecordStatic {
+                location: record.location(),
+                level: record.level(),
+                tag: record.tag(),
+            };
+            self.drain
+                .log(
+                    &Record::new(
+                        &rs,
+                        &format_args!(
+                            "last message repeated {} time{}",
+                            repeats,
+                            if repeats == 1 { "" } else { "s" }
+                        ),
+                        crate::b!("repeated" => repeats),
+                    ),
+                    values,
+                )
+                .map(|_| ())
+        })
+    }
+}
+
+impl<D: Drain> Drain for Dedup<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        let owned = OwnedRecord::new(record, logger_values);
+        // Held while logging, so follow-ups are ordered with the records
+        let mut run = self.run.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ref mut run) = *run {
+            if is_repeat(&run.first, &owned) {
+                run.repeats += 1;
+                return Ok(None);
+            }
+        }
+        let previous = run.replace(Run {
+            first: owned,
+            repeats: 0,
+        });
+        // The record is logged even if reporting the previous run failed,
+        // and the first error is returned
+        let reported = match previous {
+            Some(mut previous) => self.report(&mut previous),
+            None => Ok(()),
+        };
+        let logged = self.drain.log(record, logger_values);
+        reported?;
+        Ok(Some(logged?))
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    /// Report the repeats of the current run, if any, and flush the wrapped
+    /// drain
+    fn flush(&self) -> result::Result<(), FlushError> {
+        {
+            let mut run =
+                self.run.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(ref mut run) = *run {
+                // `FlushError` can't carry `D::Err`
+                let _ = self.report(run);
+            }
+        }
+        self.drain.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use alloc::string::{String, ToString};
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            self.0.lock().unwrap().push(record.msg().to_string());
+            Ok(())
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn collapses_runs() {
+        let collect = Collect::default();
+        let log = Logger::root(Dedup::new(collect.clone()).fuse(), o!());
+        let log_a = |i: i32| crate::info!(log, "a"; "i" => i);
+        let log_b = |log: &Logger| crate::warn!(log, "b");
+        log_a(1);
+        log_a(1);
+        log_a(1);
+        log_a(2);
+        log_a(2);
+        log_b(&log);
+        log_b(&log);
+        // Different logger values
+        let child = log.new(o!("x" => 1));
+        log_b(&child);
+        log_b(&child);
+        log.flush().unwrap();
+        log.flush().unwrap();
+        assert_eq!(
+            *collect.0.lock().unwrap(),
+            [
+                "a",
+                "last message repeated 2 times",
+                "a",
+                "last message repeated 1 time",
+                "b",
+                "last message repeated 1 time",
+                "b",
+                "last message repeated 1 time",
+            ]
+        );
+    }
+
+    #[derive(Clone, Default)]
+    struct FailingReports(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for FailingReports {
+        type Ok = ();
+        type Err = String;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), String> {
+            let msg = record.msg().to_string();
+            if msg.starts_with("last message") {
+                return Err(msg);
+            }
+            self.0.lock().unwrap().push(msg);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logs_records_despite_report_errors() {
+        let failing = FailingReports::default();
+        let log = Logger::root(
+            Dedup::new(failing.clone())
+                .map_err(|e| {
+                    assert_eq!(e, "last message repeated 1 time");
+                })
+                .ignore_res(),
+            o!(),
+        );
+        let log_a = || crate::info!(log, "a");
+        log_a();
+        log_a();
+        crate::info!(log, "b");
+        assert_eq!(*failing.0.lock().unwrap(), ["a", "b"]);
+    }
+}
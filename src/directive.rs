@@ -0,0 +1,411 @@
+//! Filtering by module path, configured with `RUST_LOG`-style directives
+//!
+//! See [`DirectiveFilter`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+use core::{fmt, result};
+
+#[cfg(has_std_error)]
+use crate::StdError;
+use crate::{
+    Drain, FilterLevel, FlushError, Level, OwnedKVList, Record,
+    LOG_LEVEL_NAMES, LOG_LEVEL_SHORT_NAMES,
+};
+
+/// Error returned when parsing invalid `Directives`
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectiveParseError {
+    /// The level of a `module=level` directive is not a valid `FilterLevel`
+    InvalidLevel {
+        /// The whole directive
+        directive: String,
+    },
+    /// A directive is not a level, and not a valid module path either
+    InvalidModule {
+        /// The whole directive
+        directive: String,
+    },
+    /// The environment variable does not contain valid unicode
+    #[cfg(feature = "std")]
+    NotUnicode {
+        /// Name of the environment variable
+        var: String,
+    },
+}
+
+impl fmt::Display for DirectiveParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DirectiveParseError::InvalidLevel { ref directive } => write!(
+                f,
+                "invalid log directive `{}`: unknown level (expected one of \
+                 off, critical, error, warning, info, debug, trace)",
+                directive
+            ),
+            DirectiveParseError::InvalidModule { ref directive } => write!(
+                f,
+                "invalid log directive `{}`: neither a level nor a module path",
+                directive
+            ),
+            #[cfg(feature = "std")]
+            DirectiveParseError::NotUnicode { ref var } => {
+                write!(f, "environment variable `{}` is not valid unicode", var)
+            }
+        }
+    }
+}
+
+#[cfg(has_std_error)]
+impl StdError for DirectiveParseError {}
+
+/// Logging levels for module paths, parsed from `RUST_LOG`-style strings
+///
+/// The string is a comma-separated list of directives, each one either:
+///
+/// * `level`: the default level, used for modules without a more specific
+///   directive (`error` if not given),
+/// * `path::to::module=level`: the level for a module and its submodules,
+/// * `path::to::module`: same as `path::to::module=trace`.
+///
+/// Levels are parsed like `FilterLevel`, case-insensitively, so `warn` and
+/// `WARNING` are both accepted. A bare word that is a full level name is
+/// taken as the default level rather than a module path.
+///
+/// When a module matches several directives, the one with the longest path
+/// is used. When the same path is given twice, the last one wins.
+///
+/// ```
+/// use slog::{Directives, FilterLevel};
+///
+/// let directives: Directives =
+///     "info,hyper=warn,my_app::db=trace".parse().unwrap();
+/// assert_eq!(directives.level_for("my_app"), FilterLevel::Info);
+/// assert_eq!(directives.level_for("hyper::client"), FilterLevel::Warning);
+/// assert_eq!(directives.level_for("my_app::db::pool"), FilterLevel::Trace);
+/// // Only whole path segments match
+/// assert_eq!(directives.level_for("hyperlocal"), FilterLevel::Info);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directives {
+    default: FilterLevel,
+    /// Sorted by decreasing length, so the first match is the longest
+    modules: Vec<(String, FilterLevel)>,
+}
+
+impl Directives {
+    /// Create `Directives` with only a default level
+    pub fn new(default: FilterLevel) -> Self {
+        Directives {
+            default,
+            modules: Vec::new(),
+        }
+    }
+
+    /// The level used for modules without a matching directive
+    pub fn default_level(&self) -> FilterLevel {
+        self.default
+    }
+
+    /// Get the level for the given module path
+    pub fn level_for(&self, module: &str) -> FilterLevel {
+        self.modules
+            .iter()
+            .find(|(prefix, _)| is_module_prefix(prefix, module))
+            .map_or(self.default, |&(_, level)| level)
+    }
+
+    /// The most verbose level of all directives
+    pub fn max_level(&self) -> FilterLevel {
+        self.modules
+            .iter()
+            .map(|&(_, level)| level)
+            .fold(self.default, core::cmp::max)
+    }
+
+    fn insert(&mut self, module: &str, level: FilterLevel) {
+        if let Some(existing) =
+            self.modules.iter_mut().find(|(m, _)| m == module)
+        {
+            existing.1 = level;
+            return;
+        }
+        let index = self
+            .modules
+            .iter()
+            .position(|(m, _)| m.len() < module.len())
+            .unwrap_or(self.modules.len());
+        self.modules.insert(index, (module.to_string(), level));
+    }
+}
+
+impl Default for Directives {
+    fn default() -> Self {
+        Directives::new(FilterLevel::Error)
+    }
+}
+
+impl FromStr for Directives {
+    type Err = DirectiveParseError;
+
+    fn from_str(s: &str) -> result::Result<Self, DirectiveParseError> {
+        let mut directives = Directives::default();
+        for directive in s.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+            let mut parts = directive.splitn(2, '=');
+            let module = parts.next().unwrap_or("").trim();
+            match parts.next() {
+                Some(level) => {
+                    if !is_module_path(module) {
+                        return Err(DirectiveParseError::InvalidModule {
+                            directive: directive.to_string(),
+                        });
+                    }
+                    let level = level.trim().parse().map_err(|()| {
+                        DirectiveParseError::InvalidLevel {
+                            directive: directive.to_string(),
+                        }
+                    })?;
+                    directives.insert(module, level);
+                }
+                None => {
+                    if let Some(level) = parse_exact_level(module) {
+                        directives.default = level;
+                    } else if is_module_path(module) {
+                        directives.insert(module, FilterLevel::Trace);
+                    } else {
+                        return Err(DirectiveParseError::InvalidModule {
+                            directive: directive.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(directives)
+    }
+}
+
+/// Parse a complete level name, unlike `FilterLevel::from_str` which
+/// also accepts prefixes such as `c` and would shadow short module names
+fn parse_exact_level(s: &str) -> Option<FilterLevel> {
+    LOG_LEVEL_NAMES
+        .iter()
+        .chain(LOG_LEVEL_SHORT_NAMES.iter())
+        .position(|name| name.eq_ignore_ascii_case(s))
+        .and_then(|i| FilterLevel::from_usize(i % LOG_LEVEL_NAMES.len()))
+}
+
+/// Check that `s` looks like a `module_path!()`, e.g. `my_app::db`
+fn is_module_path(s: &str) -> bool {
+    s.split("::").all(|segment| {
+        !segment.is_empty()
+            && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Check if `module` is `prefix` or one of its submodules
+pub(crate) fn is_module_prefix(prefix: &str, module: &str) -> bool {
+    module.starts_with(prefix)
+        && (module.len() == prefix.len()
+            || module[prefix.len()..].starts_with("::"))
+}
+
+/// `Drain` filtering records by their module, using `Directives`
+///
+/// Each record is passed on only if its level is accepted by the directive
+/// with the longest module path matching `Record::module`.
+///
+/// ```
+/// use slog::{o, Discard, DirectiveFilter, Drain};
+///
+/// let drain = DirectiveFilter::parse(Discard, "info,my_app::db=trace")
+///     .unwrap()
+///     .fuse();
+/// let _log = slog::Logger::root(drain, o!());
+/// ```
+#[derive(Debug, Clone)]
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct DirectiveFilter<D: Drain> {
+    drain: D,
+    directives: Directives,
+    max_level: FilterLevel,
+}
+
+impl<D: Drain> DirectiveFilter<D> {
+    /// Create `DirectiveFilter`
+    pub fn new(drain: D, directives: Directives) -> Self {
+        DirectiveFilter {
+            drain,
+            max_level: directives.max_level(),
+            directives,
+        }
+    }
+
+    /// Create `DirectiveFilter` from a directives string
+    ///
+    /// See `Directives` for the syntax.
+    pub fn parse(
+        drain: D,
+        directives: &str,
+    ) -> result::Result<Self, DirectiveParseError> {
+        Ok(DirectiveFilter::new(drain, directives.parse()?))
+    }
+
+    /// Create `DirectiveFilter` from the directives in an environment
+    /// variable, such as `RUST_LOG`
+    ///
+    /// If the variable is not set, only `error` and `critical` records are
+    /// logged.
+    #[cfg(feature = "std")]
+    pub fn from_env(
+        drain: D,
+        var: &str,
+    ) -> result::Result<Self, DirectiveParseError> {
+        match std::env::var(var) {
+            Ok(directives) => DirectiveFilter::parse(drain, &directives),
+            Err(std::env::VarError::NotPresent) => {
+                Ok(DirectiveFilter::new(drain, Directives::default()))
+            }
+            Err(std::env::VarError::NotUnicode(_)) => {
+                Err(DirectiveParseError::NotUnicode {
+                    var: var.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Get the directives used by this filter
+    pub fn directives(&self) -> &Directives {
+        &self.directives
+    }
+}
+
+impl<D: Drain> Drain for DirectiveFilter<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        if self
+            .directives
+            .level_for(record.module())
+            .accepts(record.level())
+        {
+            Ok(Some(self.drain.log(record, logger_values)?))
+        } else {
+            Ok(None)
+        }
+    }
+    /// `Drain::is_enabled` has no module information, so this reports the
+    /// most verbose level of all directives.
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.max_level.accepts(level) && self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_directives() {
+        let d: Directives = " warn , a::b=debug,a= TRACE ,a::b::c=off,c,"
+            .parse()
+            .unwrap();
+        assert_eq!(d.default_level(), FilterLevel::Warning);
+        assert_eq!(d.level_for("x"), FilterLevel::Warning);
+        assert_eq!(d.level_for("a"), FilterLevel::Trace);
+        assert_eq!(d.level_for("a::bb"), FilterLevel::Trace);
+        assert_eq!(d.level_for("a::b"), FilterLevel::Debug);
+        assert_eq!(d.level_for("a::b::d"), FilterLevel::Debug);
+        assert_eq!(d.level_for("a::b::c::d"), FilterLevel::Off);
+        assert_eq!(d.level_for("c"), FilterLevel::Trace);
+        assert_eq!(d.max_level(), FilterLevel::Trace);
+
+        let d: Directives = "a=info,a=error".parse().unwrap();
+        assert_eq!(d.default_level(), FilterLevel::Error);
+        assert_eq!(d.level_for("a"), FilterLevel::Error);
+        assert_eq!(d.max_level(), FilterLevel::Error);
+        assert_eq!("".parse(), Ok(Directives::default()));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "info,hyper=loud".parse::<Directives>(),
+            Err(DirectiveParseError::InvalidLevel {
+                directive: "hyper=loud".to_string()
+            })
+        );
+        for bad in &["=info", "a::=info", "my-app", "a b=info", "a:b"] {
+            assert_eq!(
+                bad.parse::<Directives>(),
+                Err(DirectiveParseError::InvalidModule {
+                    directive: bad.to_string()
+                })
+            );
+        }
+        assert_eq!(
+            "x=loud".parse::<Directives>().unwrap_err().to_string(),
+            "invalid log directive `x=loud`: unknown level (expected one of \
+             off, critical, error, warning, info, debug, trace)"
+        );
+    }
+
+    #[test]
+    fn filters_by_module() {
+        use crate::{b, o, record, Never};
+        use core::cell::Cell;
+
+        struct Count<'a>(&'a Cell<usize>);
+        impl Drain for Count<'_> {
+            type Ok = ();
+            type Err = Never;
+            fn log(
+                &self,
+                _: &Record<'_>,
+                _: &OwnedKVList,
+            ) -> result::Result<(), Never> {
+                self.0.set(self.0.get() + 1);
+                Ok(())
+            }
+        }
+
+        let count = Cell::new(0);
+        let this_module = module_path!();
+        let drain = DirectiveFilter::parse(
+            Count(&count),
+            &alloc::format!("warn,{}=debug", this_module),
+        )
+        .unwrap();
+        let values = OwnedKVList::from(o!());
+        assert_eq!(
+            drain.log(
+                &record!(Level::Debug, "", &format_args!(""), b!()),
+                &values
+            ),
+            Ok(Some(()))
+        );
+        assert_eq!(
+            drain.log(
+                &record!(Level::Trace, "", &format_args!(""), b!()),
+                &values
+            ),
+            Ok(None)
+        );
+        assert_eq!(count.get(), 1);
+        assert!(drain.is_enabled(Level::Debug));
+        assert!(!drain.is_enabled(Level::Trace));
+    }
+}
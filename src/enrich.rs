@@ -0,0 +1,352 @@
+//! Adding contextual key-value pairs to records
+//!
+//! See [`Enrich`].
+
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+use core::{fmt, result};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs, process, thread};
+
+use crate::{
+    maybe, BorrowedKV, Drain, FlushError, Key, Level, OwnedKVList, OwnedValue,
+    Record, RecordStatic, Serializer, Value, KV,
+};
+
+/// Function providing a value for `Enrich` drain
+pub trait EnrichFn<V>:
+    'static
+    + maybe::Sync
+    + maybe::Send
+    + maybe::UnwindSafe
+    + maybe::RefUnwindSafe
+    + Fn(&Record<'_>) -> V
+{
+}
+
+impl<T, V> EnrichFn<V> for T where
+    T: 'static
+        + maybe::Sync
+        + maybe::Send
+        + ?Sized
+        + maybe::UnwindSafe
+        + maybe::RefUnwindSafe
+        + Fn(&Record<'_>) -> V
+{
+}
+
+/// Provider of a value for a key, boxed in `Enrich`
+type Provider = (&'static str, Box<dyn EnrichFn<Option<OwnedValue>>>);
+
+/// Sequence numbers, shared by every `Enrich` in the process
+static SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// Next id for `Enrich::thread_id`
+static NEXT_THREAD_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    static THREAD_ID: usize = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+/// `Drain` adding key-value pairs to each record
+///
+/// Values are computed once per record, in `log`, and added to the
+/// key-value pairs of the record, after its own. Unlike `PushFnValue` in
+/// the `o!` of the root logger, they are not computed again by each drain
+/// under a `Duplicate` wrapped in `Enrich`, so every drain sees the same
+/// values.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Enrich};
+///
+/// let drain = Enrich::new(Discard)
+///     .timestamp("ts")
+///     .thread_name("thread")
+///     .seq("seq")
+///     .with("level_num", |record| record.level().as_usize());
+/// let log = slog::Logger::root(drain, o!());
+/// slog::info!(log, "enriched");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Enrich<D: Drain> {
+    drain: D,
+    providers: Vec<Provider>,
+}
+
+impl<D: Drain> Enrich<D> {
+    /// Create `Enrich`, without any providers
+    pub fn new(drain: D) -> Self {
+        Enrich {
+            drain,
+            providers: Vec::new(),
+        }
+    }
+
+    /// Add `key`, with the value returned by `f`
+    ///
+    /// Nothing is added if the value does not emit anything.
+    pub fn with<V, F>(mut self, key: &'static str, f: F) -> Self
+    where
+        V: Value,
+        F: EnrichFn<V>,
+    {
+        self.providers.push((
+            key,
+            Box::new(move |record: &Record<'_>| {
+                OwnedValue::from_value(&f(record), record)
+            }),
+        ));
+        self
+    }
+
+    /// Add `key`, with the wall-clock time in RFC 3339 format, in UTC and
+    /// with microseconds, e.g. `2001-09-09T01:46:40.000000Z`
+    pub fn timestamp(self, key: &'static str) -> Self {
+        self.with(key, |_: &Record<'_>| {
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            Rfc3339(since_epoch)
+        })
+    }
+
+    /// Add `key`, with the seconds elapsed since this method was called,
+    /// from a monotonic clock
+    pub fn monotonic(self, key: &'static str) -> Self {
+        let start = Instant::now();
+        self.with(key, move |_: &Record<'_>| start.elapsed().as_secs_f64())
+    }
+
+    /// Add `key`, with the name of the logging thread, or none if it is
+    /// unnamed
+    pub fn thread_name(self, key: &'static str) -> Self {
+        self.with(key, |_: &Record<'_>| {
+            thread::current().name().map(ToString::to_string)
+        })
+    }
+
+    /// Add `key`, with the id of the logging thread
+    ///
+    /// Ids are numbers given to threads in the order they first log through
+    /// an `Enrich`, starting at 1. They are unrelated to `ThreadId`s.
+    pub fn thread_id(self, key: &'static str) -> Self {
+        self.with(key, |_: &Record<'_>| THREAD_ID.with(|id| *id))
+    }
+
+    /// Add `key`, with the id of the process
+    pub fn pid(self, key: &'static str) -> Self {
+        let pid = process::id();
+        self.with(key, move |_: &Record<'_>| pid)
+    }
+
+    /// Add `key`, with the host name
+    ///
+    /// The host name is looked up once, by this method, from
+    /// `/proc/sys/kernel/hostname`, `/etc/hostname`, then the `HOSTNAME`
+    /// and `COMPUTERNAME` environment variables. Nothing is added if none
+    /// of them is set.
+    pub fn hostname(self, key: &'static str) -> Self {
+        let hostname = ["/proc/sys/kernel/hostname", "/etc/hostname"]
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .chain(
+                ["HOSTNAME", "COMPUTERNAME"]
+                    .iter()
+                    .filter_map(|var| env::var(var).ok()),
+            )
+            .map(|name| name.trim().to_string())
+            .find(|name| !name.is_empty());
+        self.with(key, move |_: &Record<'_>| hostname.clone())
+    }
+
+    /// Add `key`, with a sequence number
+    ///
+    /// Sequence numbers start at 0, and are shared by every `Enrich` in the
+    /// process, so records can be ordered across drains.
+    pub fn seq(self, key: &'static str) -> Self {
+        self.with(key, |_: &Record<'_>| SEQ.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl<D: Drain> Drain for Enrich<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        let extra: Vec<_> = self
+            .providers
+            .iter()
+            .filter_map(|(key, f)| f(record).map(|value| (*key, value)))
+            .collect();
+        let rs = RecordStatic {
+            location: record.location(),
+            level: record.level(),
+            tag: record.tag(),
+        };
+        let kv = EnrichKV {
+            kv: record.kv().0,
+            extra: &extra,
+        };
+        self.drain.log(
+            &Record::new(&rs, record.msg(), BorrowedKV(&kv)),
+            logger_values,
+        )
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+struct EnrichKV<'a> {
+    kv: &'a dyn KV,
+    extra: &'a [(&'static str, OwnedValue)],
+}
+
+impl KV for EnrichKV<'_> {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        self.kv.serialize(record, serializer)?;
+        for (key, value) in self.extra {
+            value.serialize(record, Key::from(*key), serializer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Time since the Unix epoch, formatted in RFC 3339
+struct Rfc3339(Duration);
+
+impl fmt::Display for Rfc3339 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        let (days, secs) = (secs / 86400, secs % 86400);
+        // Civil date from days since the epoch, in the proleptic Gregorian
+        // calendar (http://howardhinnant.github.io/date_algorithms.html)
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z % 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + u64::from(month <= 2);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            self.0.subsec_micros()
+        )
+    }
+}
+
+impl Value for Rfc3339 {
+    fn serialize(
+        &self,
+        _record: &Record<'_>,
+        key: Key,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        serializer.emit_str(key, &self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CaptureDrain;
+    use crate::{o, Duplicate, Logger};
+    use alloc::sync::Arc;
+
+    #[test]
+    fn formats_timestamps() {
+        let format = |secs, micros: u32| {
+            Rfc3339(Duration::new(secs, micros * 1000)).to_string()
+        };
+        assert_eq!(format(0, 0), "1970-01-01T00:00:00.000000Z");
+        assert_eq!(format(1_000_000_000, 5), "2001-09-09T01:46:40.000005Z");
+        assert_eq!(format(951_825_599, 0), "2000-02-29T11:59:59.000000Z");
+        assert_eq!(format(4_107_542_400, 0), "2100-03-01T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn computes_values_once_per_record() {
+        let (a, b) = (CaptureDrain::new(), CaptureDrain::new());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let drain = Enrich::new(Duplicate(a.clone(), b.clone()))
+            .timestamp("ts")
+            .monotonic("uptime")
+            .thread_id("thread_id")
+            .pid("pid")
+            .seq("seq")
+            .with("calls", move |_: &Record<'_>| {
+                counted.fetch_add(1, Ordering::Relaxed) + 1
+            });
+        let log = Logger::root(drain.fuse(), o!());
+        crate::info!(log, "a"; "key" => 1);
+        crate::info!(log, "b");
+
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        let (a, b) = (a.take(), b.take());
+        assert_eq!(a.len(), 2);
+        for (a, b) in a.iter().zip(&b) {
+            for key in ["ts", "uptime", "thread_id", "pid", "seq", "calls"] {
+                assert!(a.get(key).is_some(), "{} missing", key);
+                assert_eq!(a.get(key), b.get(key));
+            }
+        }
+        assert_eq!(a[0].get("key"), Some(&OwnedValue::I32(1)));
+        assert_eq!(a[0].get("pid"), Some(&OwnedValue::U32(process::id())));
+        assert_eq!(a[1].get("calls"), Some(&OwnedValue::Usize(2)));
+        match (a[0].get("seq"), a[1].get("seq")) {
+            (Some(OwnedValue::Usize(x)), Some(OwnedValue::Usize(y))) => {
+                assert!(x < y)
+            }
+            seqs => panic!("unexpected sequence numbers {:?}", seqs),
+        }
+    }
+
+    #[test]
+    fn numbers_threads() {
+        let capture = CaptureDrain::new();
+        let log_thread_id = |capture: CaptureDrain| {
+            let drain = Enrich::new(capture).thread_id("thread_id");
+            let log = Logger::root(drain.fuse(), o!());
+            crate::info!(log, "a");
+            crate::info!(log, "b");
+        };
+        log_thread_id(capture.clone());
+        let other = capture.clone();
+        thread::spawn(move || log_thread_id(other)).join().unwrap();
+
+        let ids: Vec<_> = capture
+            .take()
+            .iter()
+            .map(|record| record.get("thread_id").cloned())
+            .collect();
+        assert!(matches!(ids[0], Some(OwnedValue::Usize(_))));
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[2], ids[3]);
+        assert_ne!(ids[0], ids[2]);
+    }
+}
@@ -0,0 +1,366 @@
+//! Falling back to a secondary drain when the primary one fails
+//!
+//! See [`Failover`].
+
+use alloc::boxed::Box;
+use core::result;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use crate::{
+    Drain, DuplicateDrainFlushError, FlushError, Level, OwnedKVList, Record,
+};
+
+/// Which drain `Failover` is logging to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FailoverDrain {
+    /// The primary drain is working
+    Primary,
+    /// The primary drain failed, and records go to the secondary drain
+    Secondary,
+}
+
+struct Breaker {
+    /// Consecutive failures of the primary drain
+    failures: u32,
+    /// Set while the circuit is open: when to probe the primary drain again
+    retry_at: Option<Instant>,
+    /// A record is being logged to the primary drain to probe it
+    probing: bool,
+    backoff: Duration,
+}
+
+/// `Drain` logging to a secondary drain when the primary one fails
+///
+/// Works as a circuit breaker. Records are logged to the primary drain, and
+/// to the secondary drain if that fails. After `failure_threshold`
+/// consecutive failures, the primary drain is considered down, and records
+/// go straight to the secondary drain. Once the backoff has elapsed, the
+/// next record probes the primary drain again: if it succeeds, the primary
+/// drain is used again, otherwise the backoff is doubled, up to a maximum.
+///
+/// Errors of the primary drain are discarded; errors of the secondary drain
+/// are returned.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Failover, FailoverDrain};
+/// use std::time::Duration;
+///
+/// let drain = Failover::new(Discard, Discard)
+///     .failure_threshold(5)
+///     .backoff(Duration::from_millis(100), Duration::from_secs(30));
+/// assert_eq!(drain.active(), FailoverDrain::Primary);
+/// let _log = slog::Logger::root(drain.fuse(), o!());
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Failover<D1: Drain, D2: Drain> {
+    primary: D1,
+    secondary: D2,
+    failure_threshold: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    breaker: Mutex<Breaker>,
+    /// Set while `breaker` counts failures, so that records logged while
+    /// the primary drain is healthy do not take its lock
+    failing: AtomicBool,
+}
+
+/// Clears `Breaker::probing` when dropped, even if the primary drain panics
+struct Probe<'a>(Option<&'a Mutex<Breaker>>);
+
+impl Drop for Probe<'_> {
+    fn drop(&mut self) {
+        if let Some(breaker) = self.0 {
+            breaker
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .probing = false;
+        }
+    }
+}
+
+impl<D1, D2, O> Failover<D1, D2>
+where
+    D1: Drain<Ok = O>,
+    D2: Drain<Ok = O>,
+{
+    /// Create `Failover`
+    ///
+    /// By default, the primary drain is considered down after 3 consecutive
+    /// failures, and probed again after 1 second, doubling up to 1 minute.
+    pub fn new(primary: D1, secondary: D2) -> Self {
+        let initial_backoff = Duration::from_secs(1);
+        Failover {
+            primary,
+            secondary,
+            failure_threshold: 3,
+            initial_backoff,
+            max_backoff: Duration::from_secs(60),
+            breaker: Mutex::new(Breaker {
+                failures: 0,
+                retry_at: None,
+                probing: false,
+                backoff: initial_backoff,
+            }),
+            failing: AtomicBool::new(false),
+        }
+    }
+
+    /// Set the number of consecutive failures after which the primary drain
+    /// is considered down
+    ///
+    /// # Panics
+    ///
+    /// If `failure_threshold` is zero.
+    pub fn failure_threshold(mut self, failure_threshold: u32) -> Self {
+        assert!(failure_threshold > 0, "Failover threshold can't be zero");
+        self.failure_threshold = failure_threshold;
+        self
+    }
+
+    /// Set how long to wait before probing a failed primary drain, and the
+    /// maximum this wait can grow to
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self.lock().backoff = initial;
+        self
+    }
+
+    /// Get which drain records are being logged to
+    pub fn active(&self) -> FailoverDrain {
+        if self.lock().retry_at.is_some() {
+            FailoverDrain::Secondary
+        } else {
+            FailoverDrain::Primary
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Breaker> {
+        self.breaker.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Check if the next record should be logged to the primary drain
+    ///
+    /// The returned `Probe` must be kept until the record is logged.
+    fn try_primary(&self) -> Option<Probe<'_>> {
+        if !self.failing.load(Ordering::Acquire) {
+            return Some(Probe(None));
+        }
+        let mut breaker = self.lock();
+        match breaker.retry_at {
+            None => Some(Probe(None)),
+            Some(retry_at) => {
+                let now = Instant::now();
+                if now < retry_at || breaker.probing {
+                    return None;
+                }
+                breaker.probing = true;
+                Some(Probe(Some(&self.breaker)))
+            }
+        }
+    }
+
+    fn primary_succeeded(&self) {
+        if !self.failing.load(Ordering::Acquire) {
+            return;
+        }
+        let mut breaker = self.lock();
+        self.failing.store(false, Ordering::Release);
+        breaker.failures = 0;
+        breaker.retry_at = None;
+        breaker.probing = false;
+        breaker.backoff = self.initial_backoff;
+    }
+
+    fn primary_failed(&self) {
+        let mut breaker = self.lock();
+        self.failing.store(true, Ordering::Release);
+        let now = Instant::now();
+        if breaker.probing {
+            breaker.probing = false;
+            breaker.backoff = breaker
+                .backoff
+                .checked_mul(2)
+                .map_or(self.max_backoff, |b| b.min(self.max_backoff));
+            breaker.retry_at = Some(now + breaker.backoff);
+        } else if breaker.retry_at.is_none() {
+            breaker.failures += 1;
+            if breaker.failures >= self.failure_threshold {
+                breaker.retry_at = Some(now + breaker.backoff);
+            }
+        }
+    }
+}
+
+impl<D1, D2, O> Drain for Failover<D1, D2>
+where
+    D1: Drain<Ok = O>,
+    D2: Drain<Ok = O>,
+{
+    type Ok = O;
+    type Err = D2::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<O, D2::Err> {
+        if let Some(_probe) = self.try_primary() {
+            match self.primary.log(record, logger_values) {
+                Ok(ok) => {
+                    self.primary_succeeded();
+                    return Ok(ok);
+                }
+                Err(_) => self.primary_failed(),
+            }
+        }
+        self.secondary.log(record, logger_values)
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.primary.is_enabled(level) || self.secondary.is_enabled(level)
+    }
+    /// Flush both drains
+    ///
+    /// If one or both of the drains fails, this will return a
+    /// [`DuplicateDrainFlushError`], with the primary drain on the left.
+    fn flush(&self) -> result::Result<(), FlushError> {
+        let err = match (self.primary.flush(), self.secondary.flush()) {
+            (Ok(()), Ok(())) => return Ok(()),
+            (Err(primary), Ok(())) => DuplicateDrainFlushError::Left(primary),
+            (Ok(()), Err(secondary)) => {
+                DuplicateDrainFlushError::Right(secondary)
+            }
+            (Err(primary), Err(secondary)) => {
+                DuplicateDrainFlushError::Both(primary, secondary)
+            }
+        };
+        Err(FlushError::Duplicate(Box::new(err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use alloc::sync::Arc;
+    use core::panic::AssertUnwindSafe;
+    use core::sync::atomic::AtomicUsize;
+    use std::panic::catch_unwind;
+
+    #[derive(Clone, Default)]
+    struct Flaky {
+        failing: Arc<AtomicBool>,
+        panicking: Arc<AtomicBool>,
+        attempts: Arc<AtomicUsize>,
+    }
+
+    impl Drain for Flaky {
+        type Ok = ();
+        type Err = ();
+        fn log(
+            &self,
+            _: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), ()> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            if self.panicking.load(Ordering::SeqCst) {
+                panic!("primary drain panicked");
+            }
+            if self.failing.load(Ordering::SeqCst) {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct Count(Arc<AtomicUsize>);
+
+    impl Drain for Count {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            _: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn circuit_breaker() {
+        let (primary, secondary) = (Flaky::default(), Count::default());
+        let backoff = Duration::from_millis(100);
+        let failover = Arc::new(
+            Failover::new(primary.clone(), secondary.clone())
+                .failure_threshold(2)
+                .backoff(backoff, backoff * 10),
+        );
+        let log = Logger::root(failover.clone().ignore_res(), o!());
+        let counts = || {
+            (
+                primary.attempts.load(Ordering::SeqCst),
+                secondary.0.load(Ordering::SeqCst),
+            )
+        };
+
+        crate::info!(log, "primary");
+        assert_eq!(counts(), (1, 0));
+
+        primary.failing.store(true, Ordering::SeqCst);
+        crate::info!(log, "fails over");
+        assert_eq!(failover.active(), FailoverDrain::Primary);
+        crate::info!(log, "fails over, opens the circuit");
+        assert_eq!(failover.active(), FailoverDrain::Secondary);
+        assert_eq!(counts(), (3, 2));
+        crate::info!(log, "secondary");
+        assert_eq!(counts(), (3, 3));
+
+        std::thread::sleep(backoff);
+        crate::info!(log, "failed probe, doubles the backoff");
+        assert_eq!(counts(), (4, 4));
+
+        primary.failing.store(false, Ordering::SeqCst);
+        std::thread::sleep(backoff);
+        crate::info!(log, "secondary");
+        assert_eq!(counts(), (4, 5));
+        std::thread::sleep(backoff);
+        crate::info!(log, "successful probe");
+        assert_eq!(counts(), (5, 5));
+        assert_eq!(failover.active(), FailoverDrain::Primary);
+    }
+
+    #[test]
+    fn probe_panicking() {
+        let (primary, secondary) = (Flaky::default(), Count::default());
+        let failover = Arc::new(
+            Failover::new(primary.clone(), secondary)
+                .failure_threshold(1)
+                .backoff(Duration::from_secs(0), Duration::from_secs(0)),
+        );
+        let log = Logger::root(failover.clone().ignore_res(), o!());
+
+        primary.failing.store(true, Ordering::SeqCst);
+        crate::info!(log, "opens the circuit");
+        assert_eq!(failover.active(), FailoverDrain::Secondary);
+
+        primary.panicking.store(true, Ordering::SeqCst);
+        let probe = catch_unwind(AssertUnwindSafe(|| {
+            crate::info!(log, "panicking probe");
+        }));
+        assert!(probe.is_err());
+        assert_eq!(primary.attempts.load(Ordering::SeqCst), 2);
+
+        primary.panicking.store(false, Ordering::SeqCst);
+        primary.failing.store(false, Ordering::SeqCst);
+        crate::info!(log, "successful probe");
+        assert_eq!(primary.attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(failover.active(), FailoverDrain::Primary);
+    }
+}
@@ -0,0 +1,384 @@
+//! Logging to any number of drains
+//!
+//! See [`Fanout`].
+
+use alloc::vec::Vec;
+use core::{fmt, result};
+
+#[cfg(has_std_error)]
+use crate::StdError;
+use crate::{Drain, FlushError, Level, OwnedKVList, Record};
+
+/// A collection of drains `Fanout` can log to
+///
+/// Implemented for `Vec`s of drains (e.g. of boxed drains) and for tuples of
+/// up to 8 drains, as long as all the drains have the same `Drain::Err`.
+pub trait FanoutDrains {
+    /// Error type shared by all the drains
+    type Err;
+
+    /// Number of drains
+    fn len(&self) -> usize;
+
+    /// Check if there are no drains
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Log to the drain at `index`
+    fn log_at(
+        &self,
+        index: usize,
+        record: &Record<'_>,
+        values: &OwnedKVList,
+    ) -> result::Result<(), Self::Err>;
+
+    /// Call `Drain::is_enabled` on the drain at `index`
+    fn is_enabled_at(&self, index: usize, level: Level) -> bool;
+
+    /// Flush the drain at `index`
+    fn flush_at(&self, index: usize) -> result::Result<(), FlushError>;
+}
+
+impl<D: Drain> FanoutDrains for Vec<D> {
+    type Err = D::Err;
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn log_at(
+        &self,
+        index: usize,
+        record: &Record<'_>,
+        values: &OwnedKVList,
+    ) -> result::Result<(), D::Err> {
+        self[index].log(record, values).map(|_| ())
+    }
+
+    fn is_enabled_at(&self, index: usize, level: Level) -> bool {
+        self[index].is_enabled(level)
+    }
+
+    fn flush_at(&self, index: usize) -> result::Result<(), FlushError> {
+        self[index].flush()
+    }
+}
+
+macro_rules! impl_fanout_drains_for_tuple {
+    ($len:expr; $($index:tt $drain:ident),+) => {
+        impl<E, $($drain: Drain<Err = E>),+> FanoutDrains for ($($drain,)+) {
+            type Err = E;
+
+            fn len(&self) -> usize {
+                $len
+            }
+
+            fn log_at(
+                &self,
+                index: usize,
+                record: &Record<'_>,
+                values: &OwnedKVList,
+            ) -> result::Result<(), E> {
+                match index {
+                    $($index => self.$index.log(record, values).map(|_| ()),)+
+                    _ => panic!("drain index out of bounds: {}", index),
+                }
+            }
+
+            fn is_enabled_at(&self, index: usize, level: Level) -> bool {
+                match index {
+                    $($index => self.$index.is_enabled(level),)+
+                    _ => panic!("drain index out of bounds: {}", index),
+                }
+            }
+
+            fn flush_at(
+                &self,
+                index: usize,
+            ) -> result::Result<(), FlushError> {
+                match index {
+                    $($index => self.$index.flush(),)+
+                    _ => panic!("drain index out of bounds: {}", index),
+                }
+            }
+        }
+    };
+}
+
+impl_fanout_drains_for_tuple!(1; 0 D0);
+impl_fanout_drains_for_tuple!(2; 0 D0, 1 D1);
+impl_fanout_drains_for_tuple!(3; 0 D0, 1 D1, 2 D2);
+impl_fanout_drains_for_tuple!(4; 0 D0, 1 D1, 2 D2, 3 D3);
+impl_fanout_drains_for_tuple!(5; 0 D0, 1 D1, 2 D2, 3 D3, 4 D4);
+impl_fanout_drains_for_tuple!(6; 0 D0, 1 D1, 2 D2, 3 D3, 4 D4, 5 D5);
+impl_fanout_drains_for_tuple!(7; 0 D0, 1 D1, 2 D2, 3 D3, 4 D4, 5 D5, 6 D6);
+impl_fanout_drains_for_tuple!(
+    8; 0 D0, 1 D1, 2 D2, 3 D3, 4 D4, 5 D5, 6 D6, 7 D7
+);
+
+/// What `Fanout` does when one of its drains fails
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FanoutPolicy {
+    /// Keep going with the other drains, and report all the errors
+    Continue,
+    /// Skip the remaining drains, and report the first error
+    StopOnError,
+}
+
+/// Error returned by `Fanout::log`
+///
+/// Holds the errors of the drains that failed, with their indexes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanoutError<E> {
+    errors: Vec<(usize, E)>,
+}
+
+impl<E> FanoutError<E> {
+    /// The errors, with the index of the drain that returned each of them
+    pub fn errors(&self) -> &[(usize, E)] {
+        &self.errors
+    }
+
+    /// Convert into the errors, with the index of the drain that returned
+    /// each of them
+    pub fn into_errors(self) -> Vec<(usize, E)> {
+        self.errors
+    }
+}
+
+fn fmt_indexed<E: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    action: &str,
+    errors: &[(usize, E)],
+) -> fmt::Result {
+    write!(f, "Failed to {} {} drain(s):", action, errors.len())?;
+    for (index, error) in errors {
+        write!(f, " [{}] ({})", index, error)?;
+    }
+    Ok(())
+}
+
+impl<E: fmt::Display> fmt::Display for FanoutError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_indexed(f, "log to", &self.errors)
+    }
+}
+
+#[cfg(has_std_error)]
+impl<E: fmt::Debug + fmt::Display> StdError for FanoutError<E> {}
+
+/// An error from calling [`Fanout::flush`](Drain::flush)
+///
+/// Holds the errors of the drains that failed to flush, with their indexes.
+#[derive(Debug)]
+pub struct FanoutFlushError {
+    errors: Vec<(usize, FlushError)>,
+}
+
+impl FanoutFlushError {
+    /// The errors, with the index of the drain that returned each of them
+    pub fn errors(&self) -> &[(usize, FlushError)] {
+        &self.errors
+    }
+
+    /// Convert into the errors, with the index of the drain that returned
+    /// each of them
+    pub fn into_errors(self) -> Vec<(usize, FlushError)> {
+        self.errors
+    }
+}
+
+impl fmt::Display for FanoutFlushError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_indexed(f, "flush", &self.errors)
+    }
+}
+
+#[cfg(has_std_error)]
+impl StdError for FanoutFlushError {}
+
+/// `Drain` logging records to any number of drains
+///
+/// Unlike nested `Duplicate`s, errors are reported flat, in a single
+/// `FanoutError` listing each failed drain by index.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Fanout, FanoutPolicy};
+///
+/// let drain = Fanout::new((Discard, Discard, Discard))
+///     .policy(FanoutPolicy::Continue)
+///     .fuse();
+/// let _log = slog::Logger::root(drain, o!());
+/// ```
+#[derive(Debug, Clone)]
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Fanout<T> {
+    drains: T,
+    policy: FanoutPolicy,
+}
+
+impl<T: FanoutDrains> Fanout<T> {
+    /// Create `Fanout`, with the `FanoutPolicy::Continue` policy
+    pub fn new(drains: T) -> Self {
+        Fanout {
+            drains,
+            policy: FanoutPolicy::Continue,
+        }
+    }
+
+    /// Set what to do when one of the drains fails
+    ///
+    /// Applies to both logging and flushing.
+    pub fn policy(mut self, policy: FanoutPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Get the drains
+    pub fn drains(&self) -> &T {
+        &self.drains
+    }
+}
+
+impl<T: FanoutDrains> Drain for Fanout<T> {
+    type Ok = ();
+    type Err = FanoutError<T::Err>;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<(), Self::Err> {
+        let mut errors = Vec::new();
+        for index in 0..self.drains.len() {
+            if let Err(e) = self.drains.log_at(index, record, logger_values) {
+                errors.push((index, e));
+                if self.policy == FanoutPolicy::StopOnError {
+                    break;
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(FanoutError { errors })
+        }
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        (0..self.drains.len()).any(|i| self.drains.is_enabled_at(i, level))
+    }
+    /// Flush all the drains
+    ///
+    /// If any of them fails, returns a [`FanoutFlushError`] in
+    /// [`FlushError::Fanout`].
+    fn flush(&self) -> result::Result<(), FlushError> {
+        let mut errors = Vec::new();
+        for index in 0..self.drains.len() {
+            if let Err(e) = self.drains.flush_at(index) {
+                errors.push((index, e));
+                if self.policy == FanoutPolicy::StopOnError {
+                    break;
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(FlushError::Fanout(alloc::boxed::Box::new(
+                FanoutFlushError { errors },
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{b, o, record, Discard};
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+
+    /// Fails if logged at `Error` level or above
+    struct FailOnError(u32);
+
+    impl Drain for FailOnError {
+        type Ok = ();
+        type Err = u32;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), u32> {
+            if record.level().is_at_least(Level::Error) {
+                Err(self.0)
+            } else {
+                Ok(())
+            }
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            Err(FlushError::NotSupported)
+        }
+    }
+
+    struct Ok_;
+
+    impl Drain for Ok_ {
+        type Ok = ();
+        type Err = u32;
+        fn log(
+            &self,
+            _: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), u32> {
+            Ok(())
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn collects_indexed_errors() {
+        let values = OwnedKVList::from(o!());
+        let fanout = Fanout::new((FailOnError(1), Ok_, FailOnError(3)));
+        assert_eq!(
+            fanout.log(
+                &record!(Level::Info, "", &format_args!(""), b!()),
+                &values
+            ),
+            Ok(())
+        );
+        let err = fanout
+            .log(&record!(Level::Error, "", &format_args!(""), b!()), &values)
+            .unwrap_err();
+        assert_eq!(err.errors(), [(0, 1), (2, 3)]);
+        assert_eq!(
+            err.to_string(),
+            "Failed to log to 2 drain(s): [0] (1) [2] (3)"
+        );
+
+        let fanout = fanout.policy(FanoutPolicy::StopOnError);
+        let err = fanout
+            .log(&record!(Level::Error, "", &format_args!(""), b!()), &values)
+            .unwrap_err();
+        assert_eq!(err.into_errors(), [(0, 1)]);
+    }
+
+    #[test]
+    fn flushes_boxed_drains() {
+        let drains: Vec<Box<dyn Drain<Ok = (), Err = u32>>> =
+            alloc::vec![Box::new(Ok_), Box::new(FailOnError(0)), Box::new(Ok_)];
+        let fanout = Fanout::new(drains);
+        match fanout.flush() {
+            Err(FlushError::Fanout(e)) => {
+                assert_eq!(e.errors().len(), 1);
+                assert_eq!(e.errors()[0].0, 1);
+                assert!(matches!(e.errors()[0].1, FlushError::NotSupported));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(Fanout::new(alloc::vec![Discard, Discard]).flush().is_ok());
+    }
+}
@@ -0,0 +1,210 @@
+//! Keeping verbose records in memory until something goes wrong
+//!
+//! See [`FlightRecorder`].
+
+use alloc::collections::VecDeque;
+use core::result;
+use std::sync::{Mutex, PoisonError};
+
+use crate::{Drain, FlushError, Level, OwnedKVList, OwnedRecord, Record};
+
+/// `Drain` buffering verbose records, and logging them only before an error
+///
+/// Records less severe than the pass level (`Info` by default), i.e.
+/// `Debug` and `Trace` records, are not logged. Instead, owned copies of the
+/// last `capacity` of them are kept in memory. When a record at least as
+/// severe as the trigger level (`Error` by default) arrives, the buffered
+/// records are logged first, oldest first, followed by the triggering
+/// record, and the buffer is emptied. Every buffered record, and the
+/// triggering one, is logged even if logging some of them fails: the first
+/// error is returned afterwards.
+///
+/// All other records are logged right away.
+///
+/// Release builds compile out `Debug` and `Trace` records by default, so
+/// the recorder only has something to keep in them with the
+/// `release_max_level_debug` or `release_max_level_trace` cargo feature of
+/// `slog`.
+///
+/// `Drain::flush` flushes the wrapped drain. The buffered records are kept:
+/// they are only meant to be logged if a trigger record arrives.
+///
+/// ```
+/// use slog::{o, Discard, Drain, FlightRecorder, Level};
+///
+/// let drain = FlightRecorder::new(Discard, 1000)
+///     .trigger_level(Level::Warning)
+///     .fuse();
+/// let log = slog::Logger::root(drain, o!());
+/// slog::debug!(log, "kept in memory");
+/// // Logged after the debug record
+/// slog::warn!(log, "something looks wrong");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct FlightRecorder<D: Drain> {
+    drain: D,
+    capacity: usize,
+    pass_level: Level,
+    trigger_level: Level,
+    buffer: Mutex<VecDeque<OwnedRecord>>,
+}
+
+impl<D: Drain> FlightRecorder<D> {
+    /// Create `FlightRecorder` buffering up to `capacity` records
+    pub fn new(drain: D, capacity: usize) -> Self {
+        FlightRecorder {
+            drain,
+            capacity,
+            pass_level: Level::Info,
+            trigger_level: Level::Error,
+            buffer: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Set the level at which records are logged right away instead of
+    /// being buffered (default: `Info`)
+    pub fn pass_level(mut self, level: Level) -> Self {
+        self.pass_level = level;
+        self
+    }
+
+    /// Set the level at which buffered records are logged (default:
+    /// `Error`)
+    pub fn trigger_level(mut self, level: Level) -> Self {
+        self.trigger_level = level;
+        self
+    }
+}
+
+impl<D: Drain> Drain for FlightRecorder<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        if !record.level().is_at_least(self.pass_level) {
+            if self.capacity > 0 {
+                let owned = OwnedRecord::new(record, logger_values);
+                let mut buffer =
+                    self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+                if buffer.len() == self.capacity {
+                    buffer.pop_front();
+                }
+                buffer.push_back(owned);
+            }
+            return Ok(None);
+        }
+        if !record.level().is_at_least(self.trigger_level) {
+            return self.drain.log(record, logger_values).map(Some);
+        }
+        // Held until the triggering record is logged, so the history and
+        // the record are logged in one piece
+        let mut buffer =
+            self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut first_err = None;
+        for buffered in buffer.drain(..) {
+            if let Err(e) = buffered.replay(&self.drain) {
+                first_err.get_or_insert(e);
+            }
+        }
+        let logged = self.drain.log(record, logger_values);
+        match first_err {
+            Some(e) => Err(e),
+            None => logged.map(Some),
+        }
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use alloc::string::{String, ToString};
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            self.0.lock().unwrap().push(record.msg().to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn emits_history_on_trigger() {
+        let collect = Collect::default();
+        let log =
+            Logger::root(FlightRecorder::new(collect.clone(), 2).fuse(), o!());
+        crate::trace!(log, "t1");
+        crate::debug!(log, "d1");
+        crate::info!(log, "i1");
+        crate::debug!(log, "d2");
+        crate::warn!(log, "w1");
+        crate::error!(log, "e1");
+        crate::crit!(log, "c1");
+        crate::debug!(log, "d3");
+        assert_eq!(
+            *collect.0.lock().unwrap(),
+            ["i1", "w1", "d1", "d2", "e1", "c1"]
+        );
+    }
+    /// Fails on records whose message starts with `fail`
+    #[derive(Clone, Default)]
+    struct Failing(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Failing {
+        type Ok = ();
+        type Err = String;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), String> {
+            let msg = record.msg().to_string();
+            if msg.starts_with("fail") {
+                return Err(msg);
+            }
+            self.0.lock().unwrap().push(msg);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logs_all_history_despite_errors() {
+        let failing = Failing::default();
+        let log = Logger::root(
+            FlightRecorder::new(failing.clone(), 4)
+                .map_err(|e| {
+                    assert_eq!(e, "fail1");
+                })
+                .ignore_res(),
+            o!(),
+        );
+        crate::debug!(log, "d1");
+        crate::debug!(log, "fail1");
+        crate::debug!(log, "fail2");
+        crate::debug!(log, "d2");
+        crate::error!(log, "e1");
+        crate::error!(log, "e2");
+        assert_eq!(*failing.0.lock().unwrap(), ["d1", "d2", "e1", "e2"]);
+    }
+}
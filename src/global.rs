@@ -0,0 +1,148 @@
+//! Process-wide global `Logger`
+//!
+//! Libraries that do not take a `Logger` parameter can log to [`logger()`],
+//! which discards everything until the application calls [`set_logger`].
+//!
+//! ```
+//! use slog::{o, Discard, Logger};
+//!
+//! let _guard = slog::global::set_logger(Logger::root(Discard, o!()));
+//! slog::info!(slog::global::logger(), "logged to the global logger");
+//! // Dropping `_guard` flushes it, and restores the previous one
+//! ```
+//!
+//! Outside of any [`scope`](crate::scope::scope), the `slog::scope` macros
+//! log to the global `Logger` too.
+//!
+//! Not available with the `nothreads` feature, since the `Logger` is shared
+//! by all threads.
+
+use core::mem;
+use core::ptr;
+use core::sync::atomic::AtomicPtr;
+use std::sync::{PoisonError, RwLock};
+
+use crate::intern::leak_once;
+use crate::{o, Discard, Logger};
+
+static GLOBAL: AtomicPtr<RwLock<Logger>> = AtomicPtr::new(ptr::null_mut());
+
+/// Get the global `RwLock`, creating it on first use
+fn global() -> &'static RwLock<Logger> {
+    leak_once(&GLOBAL, || RwLock::new(Logger::root(Discard, o!())))
+}
+
+/// Get the global `Logger`
+///
+/// This is a `Logger` discarding everything, unless [`set_logger`] was
+/// called.
+pub fn logger() -> Logger {
+    global()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Replace the global `Logger`
+///
+/// The returned guard flushes `logger`, and restores the previous global
+/// `Logger`, when dropped. Guards should be dropped in the reverse order
+/// they were created in, or the restored `Logger` may not be the expected
+/// one.
+pub fn set_logger(logger: Logger) -> GlobalLoggerGuard {
+    let previous = mem::replace(
+        &mut *global().write().unwrap_or_else(PoisonError::into_inner),
+        logger,
+    );
+    GlobalLoggerGuard {
+        previous: Some(previous),
+    }
+}
+
+/// Guard restoring the previous global `Logger` when dropped
+///
+/// See [`set_logger`].
+#[must_use = "the previous global logger is restored when this is dropped"]
+pub struct GlobalLoggerGuard {
+    previous: Option<Logger>,
+}
+
+impl GlobalLoggerGuard {
+    /// Keep the global `Logger` set, rather than restoring the previous one
+    ///
+    /// The global `Logger` is not flushed either.
+    pub fn cancel_reset(mut self) {
+        self.previous = None;
+    }
+}
+
+impl Drop for GlobalLoggerGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            let current = mem::replace(
+                &mut *global().write().unwrap_or_else(PoisonError::into_inner),
+                previous,
+            );
+            // Outside of the lock, in case the drain logs globally
+            let _ = current.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Drain, FlushError, Never, OwnedKVList, Record};
+    use alloc::string::{String, ToString};
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+    use core::result;
+    use std::sync::Mutex;
+
+    /// Collects messages of this test, and counts flushes
+    #[derive(Clone, Default)]
+    struct Collect {
+        msgs: Arc<Mutex<Vec<String>>>,
+        flushes: Arc<Mutex<usize>>,
+    }
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            // Other tests may log globally at the same time
+            if record.tag() == "global-test" {
+                self.msgs.lock().unwrap().push(record.msg().to_string());
+            }
+            Ok(())
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            *self.flushes.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn replaces_and_restores() {
+        let (outer, inner) = (Collect::default(), Collect::default());
+        let outer_guard = set_logger(Logger::root(outer.clone(), o!()));
+        crate::info!(logger(), #"global-test", "a");
+        {
+            let _inner_guard = set_logger(Logger::root(inner.clone(), o!()));
+            crate::info!(logger(), #"global-test", "b");
+            crate::scope::info!(#"global-test", "c");
+        }
+        assert_eq!(*inner.flushes.lock().unwrap(), 1);
+        crate::info!(logger(), #"global-test", "d");
+        drop(outer_guard);
+        crate::info!(logger(), #"global-test", "discarded");
+
+        assert_eq!(*outer.msgs.lock().unwrap(), ["a", "d"]);
+        assert_eq!(*inner.msgs.lock().unwrap(), ["b", "c"]);
+        assert_eq!(*outer.flushes.lock().unwrap(), 1);
+    }
+}
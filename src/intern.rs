@@ -0,0 +1,91 @@
+//! Process-wide interning of runtime strings into `&'static str`s
+//!
+//! Keys are `&'static str` unless the `dynamic-keys` feature is enabled,
+//! and record locations always are, so drains rewriting them at runtime
+//! need to leak strings. Interning leaks each distinct string at most once,
+//! and at most [`MAX_INTERNED`] strings overall.
+//!
+//! [`leak_once`] lazily creates such process-wide values.
+
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use std::collections::HashSet;
+use std::sync::{PoisonError, RwLock};
+
+/// Get the value of `slot`, leaking `init()` into it on first use
+///
+/// Stands in for a `static` holding a `Mutex` or an `RwLock`, whose `new` is
+/// not `const` in our MSRV. `slot` must start null, and only be set here.
+#[cfg_attr(
+    all(feature = "dynamic-keys", feature = "nothreads"),
+    allow(dead_code)
+)]
+pub(crate) fn leak_once<T>(slot: &AtomicPtr<T>, init: fn() -> T) -> &'static T {
+    let current = slot.load(Ordering::Acquire);
+    if !current.is_null() {
+        // SAFETY: `slot` is only ever set to a leaked `Box`, never freed
+        return unsafe { &*current };
+    }
+    let new: &'static T = Box::leak(Box::new(init()));
+    match slot.compare_exchange(
+        ptr::null_mut(),
+        new as *const T as *mut T,
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    ) {
+        Ok(_) => new,
+        // Another thread won the race; leaking `new` is harmless since this
+        // only happens once per slot
+        // SAFETY: `slot` is only ever set to a leaked `Box`, never freed
+        Err(existing) => unsafe { &*existing },
+    }
+}
+
+/// Most distinct strings leaked by [`intern`]
+pub(crate) const MAX_INTERNED: usize = 4096;
+
+static INTERNED: AtomicPtr<RwLock<HashSet<&'static str>>> =
+    AtomicPtr::new(ptr::null_mut());
+
+/// Get a `'static` copy of `s`
+///
+/// Returns `None` if `s` was not interned yet, and `MAX_INTERNED` strings
+/// already are.
+#[cfg_attr(feature = "dynamic-keys", allow(dead_code))]
+pub(crate) fn intern(s: &str) -> Option<&'static str> {
+    let strings = leak_once(&INTERNED, || RwLock::new(HashSet::new()));
+    let found = strings
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(s)
+        .copied();
+    if found.is_some() {
+        return found;
+    }
+    let mut interned = strings.write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(&s) = interned.get(s) {
+        return Some(s);
+    }
+    if interned.len() >= MAX_INTERNED {
+        return None;
+    }
+    let s = Box::leak(s.to_string().into_boxed_str());
+    interned.insert(s);
+    Some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    #[test]
+    fn interns_once() {
+        let a = intern(&String::from("intern-test")).unwrap();
+        let b = intern(&String::from("intern-test")).unwrap();
+        assert_eq!(a, "intern-test");
+        assert!(ptr::eq(a, b));
+    }
+}
@@ -0,0 +1,400 @@
+//! Filtering records by their key-value pairs
+//!
+//! See [`KvFilter`].
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::{fmt, ops, result};
+
+use crate::owned::CaptureSerializer;
+use crate::{
+    Drain, FlushError, Key, Level, OwnedKVList, OwnedValue, Record, Value, KV,
+};
+
+#[derive(Debug, Clone)]
+enum Predicate {
+    Compare(String, Ordering, bool, OwnedValue),
+    Exists(String),
+    All(Vec<KvPredicate>),
+    Any(Vec<KvPredicate>),
+    Not(Box<KvPredicate>),
+}
+
+/// Condition on the key-value pairs of a record, for `KvFilter`
+///
+/// Comparisons are typed: numbers compare by value whatever their type
+/// (e.g. a `u16` with an `i32`), strings with `fmt::Arguments` (`%` in
+/// logging macros) by their text, `bool`s and `char`s with each other.
+/// Comparing values that can't be compared, or with a key that is not
+/// there, is always false, for `ne` too. Use `!KvPredicate::eq(..)` to
+/// also match records without the key.
+///
+/// Combine predicates with `and`, `or`, and `!`.
+///
+/// ```
+/// use slog::KvPredicate;
+///
+/// let slow_for_acme = KvPredicate::eq("tenant", "acme")
+///     .and(KvPredicate::gt("latency_ms", 500));
+/// let not_debugging = !KvPredicate::exists("debug");
+/// ```
+#[derive(Debug, Clone)]
+pub struct KvPredicate(Predicate);
+
+impl KvPredicate {
+    fn compare<V: Value>(
+        key: &str,
+        ordering: Ordering,
+        matches: bool,
+        value: V,
+    ) -> Self {
+        let value = OwnedValue::from_value(
+            &value,
+            &crate::record!(Level::Info, "", &format_args!(""), crate::b!()),
+        )
+        .unwrap_or(OwnedValue::None);
+        KvPredicate(Predicate::Compare(
+            key.to_string(),
+            ordering,
+            matches,
+            value,
+        ))
+    }
+
+    /// `key` equals `value`
+    pub fn eq<V: Value>(key: &str, value: V) -> Self {
+        Self::compare(key, Ordering::Equal, true, value)
+    }
+
+    /// `key` is present, and comparable with but not equal to `value`
+    pub fn ne<V: Value>(key: &str, value: V) -> Self {
+        Self::compare(key, Ordering::Equal, false, value)
+    }
+
+    /// `key` is less than `value`
+    pub fn lt<V: Value>(key: &str, value: V) -> Self {
+        Self::compare(key, Ordering::Less, true, value)
+    }
+
+    /// `key` is less than or equal to `value`
+    pub fn le<V: Value>(key: &str, value: V) -> Self {
+        Self::compare(key, Ordering::Greater, false, value)
+    }
+
+    /// `key` is greater than `value`
+    pub fn gt<V: Value>(key: &str, value: V) -> Self {
+        Self::compare(key, Ordering::Greater, true, value)
+    }
+
+    /// `key` is greater than or equal to `value`
+    pub fn ge<V: Value>(key: &str, value: V) -> Self {
+        Self::compare(key, Ordering::Less, false, value)
+    }
+
+    /// `key` is present, with any value
+    pub fn exists(key: &str) -> Self {
+        KvPredicate(Predicate::Exists(key.to_string()))
+    }
+
+    /// Both this and `other` are true
+    pub fn and(self, other: KvPredicate) -> Self {
+        match self.0 {
+            Predicate::All(mut all) => {
+                all.push(other);
+                KvPredicate(Predicate::All(all))
+            }
+            _ => KvPredicate(Predicate::All(alloc::vec![self, other])),
+        }
+    }
+
+    /// This or `other` is true
+    pub fn or(self, other: KvPredicate) -> Self {
+        match self.0 {
+            Predicate::Any(mut any) => {
+                any.push(other);
+                KvPredicate(Predicate::Any(any))
+            }
+            _ => KvPredicate(Predicate::Any(alloc::vec![self, other])),
+        }
+    }
+
+    fn keys<'a>(&'a self, keys: &mut Vec<&'a str>) {
+        match self.0 {
+            Predicate::Compare(ref key, ..) | Predicate::Exists(ref key) => {
+                if !keys.contains(&key.as_str()) {
+                    keys.push(key);
+                }
+            }
+            Predicate::All(ref predicates) | Predicate::Any(ref predicates) => {
+                for predicate in predicates {
+                    predicate.keys(keys);
+                }
+            }
+            Predicate::Not(ref predicate) => predicate.keys(keys),
+        }
+    }
+
+    fn eval<'v>(&self, get: &dyn Fn(&str) -> Option<&'v OwnedValue>) -> bool {
+        match self.0 {
+            Predicate::Compare(ref key, ordering, matches, ref value) => {
+                match get(key).and_then(|actual| actual.loose_cmp(value)) {
+                    Some(actual) => (actual == ordering) == matches,
+                    None => false,
+                }
+            }
+            Predicate::Exists(ref key) => get(key).is_some(),
+            Predicate::All(ref predicates) => {
+                predicates.iter().all(|predicate| predicate.eval(get))
+            }
+            Predicate::Any(ref predicates) => {
+                predicates.iter().any(|predicate| predicate.eval(get))
+            }
+            Predicate::Not(ref predicate) => !predicate.eval(get),
+        }
+    }
+}
+
+impl ops::Not for KvPredicate {
+    type Output = KvPredicate;
+
+    fn not(self) -> KvPredicate {
+        match self.0 {
+            Predicate::Not(predicate) => *predicate,
+            _ => KvPredicate(Predicate::Not(Box::new(self))),
+        }
+    }
+}
+
+type Pairs = Vec<(Key, OwnedValue)>;
+
+/// `Drain` filtering records on their key-value pairs
+///
+/// Records are passed on only if the `KvPredicate` holds for their
+/// key-value pairs, looked up first in the record, then in the logger.
+///
+/// Only the values of keys used by the predicate are captured. The logger
+/// key-value pairs are only serialized if the record lacks some of these
+/// keys, and are then evaluated for each record, so values computed from
+/// the record, like `FnValue`s, are up to date.
+///
+/// ```
+/// use slog::{o, Discard, Drain, KvFilter, KvPredicate};
+///
+/// let drain = KvFilter::new(
+///     Discard,
+///     KvPredicate::eq("tenant", "acme").or(KvPredicate::gt("latency_ms", 500)),
+/// )
+/// .fuse();
+/// let log = slog::Logger::root(drain, o!("tenant" => "acme"));
+/// slog::info!(log, "request"; "latency_ms" => 20);
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct KvFilter<D: Drain> {
+    drain: D,
+    predicate: KvPredicate,
+    keys: Vec<String>,
+}
+
+impl<D: Drain> KvFilter<D> {
+    /// Create `KvFilter` passing on records for which `predicate` holds
+    pub fn new(drain: D, predicate: KvPredicate) -> Self {
+        let mut keys = Vec::new();
+        predicate.keys(&mut keys);
+        let keys = keys.into_iter().map(str::to_string).collect();
+        KvFilter {
+            drain,
+            predicate,
+            keys,
+        }
+    }
+
+    /// Capture the values of the keys used by the predicate
+    fn capture<K: KV + ?Sized>(&self, kv: &K, record: &Record<'_>) -> Pairs {
+        let wanted = |key: &str| self.keys.iter().any(|k| k == key);
+        let mut pairs = Vec::new();
+        let _ = kv.serialize(
+            record,
+            &mut CaptureSerializer::filtered(&mut pairs, &wanted),
+        );
+        pairs
+    }
+
+    fn matches(&self, record: &Record<'_>, values: &OwnedKVList) -> bool {
+        let mut pairs = self.capture(&record.kv(), record);
+        let has = |pairs: &Pairs, key: &str| {
+            pairs.iter().any(|(k, _)| AsRef::<str>::as_ref(k) == key)
+        };
+        // The record's own pairs take precedence, so the logger is only
+        // looked at for keys the record lacks
+        if !self.keys.iter().all(|key| has(&pairs, key)) {
+            let inherited = self.capture(values, record);
+            pairs.extend(inherited);
+        }
+        let get = |key: &str| {
+            pairs
+                .iter()
+                .find(|(k, _)| AsRef::<str>::as_ref(k) == key)
+                .map(|(_, v)| v)
+        };
+        self.predicate.eval(&get)
+    }
+}
+
+impl<D: Drain> Drain for KvFilter<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        if self.matches(record, logger_values) {
+            self.drain.log(record, logger_values).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+impl<D: Drain> fmt::Debug for KvFilter<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KvFilter")
+            .field("predicate", &self.predicate)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, FnValue, Logger, Never};
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    #[derive(Clone, Default)]
+    struct Count(Arc<AtomicUsize>);
+
+    impl Count {
+        fn take(&self) -> usize {
+            self.0.swap(0, AtomicOrdering::Relaxed)
+        }
+    }
+
+    impl Drain for Count {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            _: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            self.0.fetch_add(1, AtomicOrdering::Relaxed);
+            Ok(())
+        }
+    }
+
+    /// Counts how many times it is serialized
+    #[derive(Clone)]
+    struct Tenant(&'static str, Arc<AtomicUsize>);
+
+    impl Value for Tenant {
+        fn serialize(
+            &self,
+            _: &Record<'_>,
+            key: Key,
+            serializer: &mut dyn crate::Serializer,
+        ) -> crate::Result {
+            self.1.fetch_add(1, AtomicOrdering::Relaxed);
+            serializer.emit_str(key, self.0)
+        }
+    }
+
+    #[test]
+    fn typed_comparisons() {
+        let count = Count::default();
+        let log = Logger::root(
+            KvFilter::new(
+                count.clone(),
+                KvPredicate::gt("latency_ms", 500)
+                    .or(KvPredicate::eq("path", "/admin"))
+                    .or(KvPredicate::le("ratio", 0.5)),
+            )
+            .ignore_res(),
+            o!(),
+        );
+        crate::info!(log, "a"; "latency_ms" => 501u64);
+        crate::info!(log, "a"; "latency_ms" => 500.5f32);
+        crate::info!(log, "a"; "path" => %"/admin");
+        crate::info!(log, "a"; "ratio" => 0.5);
+        crate::info!(log, "a"; "ratio" => 1);
+        assert_eq!(count.take(), 4);
+
+        crate::info!(log, "a"; "latency_ms" => 500);
+        crate::info!(log, "a"; "latency_ms" => "fast");
+        crate::info!(log, "a"; "path" => "/");
+        crate::info!(log, "a");
+        assert_eq!(count.take(), 0);
+
+        let log = Logger::root(
+            KvFilter::new(
+                count.clone(),
+                !KvPredicate::ne("ok", true).and(KvPredicate::exists("id")),
+            )
+            .ignore_res(),
+            o!(),
+        );
+        crate::info!(log, "a"; "ok" => true);
+        crate::info!(log, "a"; "ok" => false, "id" => 1);
+        crate::info!(log, "a"; "id" => 1);
+        assert_eq!(count.take(), 2);
+    }
+
+    #[test]
+    fn evaluates_logger_values_per_record() {
+        let count = Count::default();
+        let serialized = Arc::new(AtomicUsize::new(0));
+        let root = Logger::root(
+            KvFilter::new(count.clone(), KvPredicate::eq("tenant", "acme"))
+                .ignore_res(),
+            o!("tenant" => Tenant("other", serialized.clone())),
+        );
+        let acme = root.new(o!("tenant" => Tenant("acme", serialized.clone())));
+        for _ in 0..10 {
+            crate::info!(acme, "a");
+            crate::info!(root, "b");
+        }
+        // The record's own key-value pairs take precedence, and the logger
+        // is not serialized
+        crate::info!(root, "c"; "tenant" => "acme");
+        assert_eq!(count.take(), 11);
+        // Once per record for `root`, twice (both tenants) for `acme`
+        assert_eq!(serialized.load(AtomicOrdering::Relaxed), 30);
+
+        // Lazy values are evaluated for each record
+        let calls = Arc::new(AtomicUsize::new(0));
+        let lazy = {
+            let calls = calls.clone();
+            root.new(o!("tenant" => FnValue(move |_| {
+                match calls.fetch_add(1, AtomicOrdering::Relaxed) % 2 {
+                    0 => "acme",
+                    _ => "other",
+                }
+            })))
+        };
+        for _ in 0..4 {
+            crate::info!(lazy, "d");
+        }
+        assert_eq!(count.take(), 2);
+        assert_eq!(calls.load(AtomicOrdering::Relaxed), 4);
+    }
+}
@@ -39,8 +39,8 @@
 //!   fast](https://github.com/slog-rs/slog/wiki/What-makes-slog-fast) and see:
 //!   [slog bench log](https://github.com/dpc/slog-rs/wiki/Bench-log)
 //!   * lazily evaluation through closure values
-//!   * async IO support included: see [`slog-async`
-//!     crate](https://docs.rs/slog-async)
+//!   * async IO support included: see `Async` (requires `std`), or the
+//!     [`slog-async` crate](https://docs.rs/slog-async)
 //! * `#![no_std]` support (with opt-out `std` cargo feature flag)
 //! * support for named format arguments (e.g. `info!(logger, "printed {line_count} lines", line_count = 2);`)
 //!   for easy bridging between the human readable and machine-readable outputs
@@ -72,7 +72,8 @@
 //!    and [journald](https://docs.rs/slog-journald) support
 //!  * run-time configuration:
 //!      * run-time behavior change;
-//!        see [slog-atomic](https://docs.rs/slog-atomic)
+//!        see `Switch` (requires `std`),
+//!        or [slog-atomic](https://docs.rs/slog-atomic)
 //!      * run-time configuration; see
 //!        [slog-config crate](https://docs.rs/slog-config)
 //!
@@ -154,74 +155,24 @@
 //!
 //! ### Change logging level at runtime
 //!
-//! ```ignore
-//! use slog::{o, Drain};
-//!
-//! use std::sync::{Arc, atomic};
-//! use std::sync::atomic::Ordering;
-//! use std::result;
-//!
-//! /// Custom Drain logic
-//! struct RuntimeLevelFilter<D>{
-//!    drain: D,
-//!    on: Arc<atomic::AtomicBool>,
-//! }
-//!
-//! impl<D> Drain for RuntimeLevelFilter<D>
-//!     where D : Drain {
-//!     type Ok = Option<D::Ok>;
-//!     type Err = Option<D::Err>;
-//!
-//!     fn log(&self,
-//!           record: &slog::Record,
-//!           values: &slog::OwnedKVList)
-//!           -> result::Result<Self::Ok, Self::Err> {
-//!           let current_level = if self.on.load(Ordering::Relaxed) {
-//!               slog::Level::Trace
-//!           } else {
-//!               slog::Level::Info
-//!           };
-//!
-//!           if record.level().is_at_least(current_level) {
-//!               self.drain.log(
-//!                   record,
-//!                   values
-//!               )
-//!               .map(Some)
-//!               .map_err(Some)
-//!           } else {
-//!               Ok(None)
-//!           }
-//!       }
-//!   }
+//! ```
+//! use slog::{o, Drain, FilterLevel, ReloadableLevelFilter};
 //!
 //! fn main() {
-//!     // atomic variable controlling logging level
-//!     let on = Arc::new(atomic::AtomicBool::new(false));
-//!
-//!     let decorator = slog_term::TermDecorator::new().build();
-//!     let drain = slog_term::FullFormat::new(decorator).build();
-//!     let drain = RuntimeLevelFilter {
-//!         drain: drain,
-//!         on: on.clone(),
-//!     }.fuse();
-//!     let drain = slog_async::Async::new(drain).build().fuse();
+//!     let drain = slog::Discard;
+//!     let drain = ReloadableLevelFilter::new(drain, FilterLevel::Info);
+//!     // cloneable handle controlling the logging level
+//!     let handle = drain.handle();
 //!
-//!     let _log = slog::Logger::root(drain, o!());
+//!     let _log = slog::Logger::root(drain.fuse(), o!());
 //!
 //!     // switch level in your code
-//!     on.store(true, Ordering::Relaxed);
+//!     handle.set_level(FilterLevel::Trace);
 //! }
 //! ```
 //!
-//! Why is this not an existing crate? Because there are multiple ways to
-//! achieve the same result, and each application might come with its own
-//! variation. Supporting a more general solution is a maintenance effort.
-//! There is also nothing stopping anyone from publishing their own crate
-//! implementing it.
-//!
-//! Alternative to the above approach is `slog-atomic` crate. It implements
-//! swapping whole parts of `Drain` logging hierarchy.
+//! Alternative to the above approach is `Switch` (requires `std`). It
+//! implements swapping whole parts of `Drain` logging hierarchy.
 //!
 //! ## Examples & help
 //!
@@ -305,6 +256,106 @@ extern crate self as slog;
 
 mod key;
 pub use self::key::Key;
+#[cfg(feature = "std")]
+mod async_drain;
+#[cfg(feature = "std")]
+pub use self::async_drain::{
+    Async, AsyncBuilder, AsyncError, OverflowStrategy,
+};
+#[cfg(feature = "std")]
+mod catch_unwind;
+#[cfg(feature = "std")]
+pub use self::catch_unwind::{CatchUnwind, CatchUnwindError, PanicError};
+#[cfg(feature = "std")]
+mod dedup;
+#[cfg(feature = "std")]
+pub use self::dedup::Dedup;
+#[cfg(feature = "std")]
+mod enrich;
+#[cfg(feature = "std")]
+pub use self::enrich::{Enrich, EnrichFn};
+#[cfg(feature = "std")]
+mod failover;
+#[cfg(feature = "std")]
+pub use self::failover::{Failover, FailoverDrain};
+mod fanout;
+pub use self::fanout::{
+    Fanout, FanoutDrains, FanoutError, FanoutFlushError, FanoutPolicy,
+};
+#[cfg(feature = "std")]
+mod flight_recorder;
+#[cfg(feature = "std")]
+pub use self::flight_recorder::FlightRecorder;
+mod directive;
+#[cfg(all(feature = "std", not(feature = "nothreads")))]
+pub mod global;
+#[cfg(feature = "std")]
+mod intern;
+pub use self::directive::{DirectiveFilter, DirectiveParseError, Directives};
+#[cfg(feature = "std")]
+mod kv_filter;
+#[cfg(feature = "std")]
+pub use self::kv_filter::{KvFilter, KvPredicate};
+#[cfg(all(feature = "log", not(feature = "nothreads")))]
+mod log_bridge;
+#[cfg(all(feature = "log", not(feature = "nothreads")))]
+pub use self::log_bridge::LogBridge;
+#[cfg(feature = "log")]
+mod log_drain;
+#[cfg(feature = "log")]
+pub use self::log_drain::LogDrain;
+#[cfg(feature = "std")]
+mod map_keys;
+#[cfg(feature = "std")]
+pub use self::map_keys::{KeyCase, MapKeys};
+#[cfg(feature = "std")]
+mod metrics;
+#[cfg(feature = "std")]
+pub use self::metrics::{Latency, Metrics, MetricsHandle, MetricsSnapshot};
+#[cfg(feature = "std")]
+mod owned;
+#[cfg(feature = "std")]
+mod rate_limit;
+#[cfg(all(feature = "std", feature = "nested-values"))]
+pub use self::owned::OwnedSerdeValue;
+#[cfg(feature = "std")]
+pub use self::owned::{OwnedError, OwnedRecord, OwnedValue};
+#[cfg(feature = "std")]
+pub use self::rate_limit::RateLimit;
+#[cfg(feature = "std")]
+mod redact;
+#[cfg(feature = "std")]
+pub use self::redact::{Redact, RedactMode};
+#[cfg(feature = "std")]
+mod remap;
+#[cfg(feature = "std")]
+pub use self::remap::Remap;
+#[cfg(feature = "std")]
+mod retry;
+#[cfg(feature = "std")]
+pub use self::retry::{Retry, RetryIfFn};
+#[cfg(feature = "std")]
+mod sample;
+#[cfg(feature = "std")]
+pub use self::sample::Sample;
+#[cfg(feature = "std")]
+pub mod scope;
+#[cfg(feature = "std")]
+mod switch;
+#[cfg(feature = "std")]
+pub use self::switch::{Switch, SwitchCtrl};
+mod tag_router;
+pub use self::tag_router::TagRouter;
+#[cfg(feature = "std")]
+pub mod testing;
+#[cfg(feature = "tracing")]
+mod tracing_drain;
+#[cfg(feature = "tracing")]
+pub use self::tracing_drain::TracingDrain;
+#[cfg(all(feature = "tracing", not(feature = "nothreads")))]
+mod tracing_layer;
+#[cfg(all(feature = "tracing", not(feature = "nothreads")))]
+pub use self::tracing_layer::TracingLayer;
 
 use alloc::borrow::{Cow, ToOwned};
 use alloc::boxed::Box;
@@ -313,6 +364,7 @@ use alloc::string::String;
 use alloc::{sync::Arc, vec::Vec};
 
 use core::str::FromStr;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use core::{convert, fmt, result};
 
 #[cfg(all(not(feature = "std"), has_std_error))]
@@ -1197,6 +1249,8 @@ pub enum FlushError {
     Custom(Box<dyn StdError + Send + Sync + 'static>),
     /// An error caused by calling [`slog::Duplicate::flush`].
     Duplicate(Box<DuplicateDrainFlushError>),
+    /// An error caused by calling [`Fanout::flush`](Drain::flush).
+    Fanout(Box<FanoutFlushError>),
 }
 #[cfg(feature = "std")]
 impl From<std::io::Error> for FlushError {
@@ -1214,6 +1268,7 @@ impl StdError for FlushError {
             #[cfg(has_std_error)]
             FlushError::Custom(cause) => Some(&**cause),
             FlushError::Duplicate(cause) => Some(cause),
+            FlushError::Fanout(cause) => Some(cause),
         }
     }
 }
@@ -1232,6 +1287,7 @@ impl fmt::Display for FlushError {
                 write!(f, "Encountered error during flushing: {cause}")
             }
             FlushError::Duplicate(cause) => write!(f, "{cause}"),
+            FlushError::Fanout(cause) => write!(f, "{cause}"),
         }
     }
 }
@@ -1359,7 +1415,7 @@ pub trait Drain {
     /// ```
     /// In this case, the drain is only required to flush `record1`.
     /// It may or may not flush `record2`.
-    /// This is mainly relevant for the implementation of [`slog_async::Async`],
+    /// This is mainly relevant for the implementation of `Async` and [`slog_async::Async`],
     /// as we have no control over the implementation of [`std::io::Write::flush`].
     /// This behavior is chosen to prevent a flush call from blocking indefinitely
     /// in the case of concurrent logging by other threads.
@@ -1868,6 +1924,81 @@ impl<D: Drain> Drain for LevelFilter<D> {
     }
 }
 
+/// `Drain` filtering records by a logging level that can change at runtime
+///
+/// Like `LevelFilter`, but the level is stored atomically and controlled
+/// through a `LevelFilterHandle`, which can be cloned and sent to other
+/// threads.
+#[derive(Debug, Clone)]
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct ReloadableLevelFilter<D: Drain> {
+    drain: D,
+    handle: LevelFilterHandle,
+}
+
+impl<D: Drain> ReloadableLevelFilter<D> {
+    /// Create `ReloadableLevelFilter` with the given initial level
+    pub fn new(drain: D, level: FilterLevel) -> Self {
+        ReloadableLevelFilter {
+            drain,
+            handle: LevelFilterHandle(Arc::new(AtomicUsize::new(
+                level.as_usize(),
+            ))),
+        }
+    }
+
+    /// Get a handle controlling the level of this filter
+    pub fn handle(&self) -> LevelFilterHandle {
+        self.handle.clone()
+    }
+}
+
+impl<D: Drain> Drain for ReloadableLevelFilter<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        if self.handle.level().accepts(record.level()) {
+            Ok(Some(self.drain.log(record, logger_values)?))
+        } else {
+            Ok(None)
+        }
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.handle.level().accepts(level) && self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+/// Handle controlling the level of a `ReloadableLevelFilter`
+///
+/// All clones control the same filter.
+#[derive(Debug, Clone)]
+pub struct LevelFilterHandle(Arc<AtomicUsize>);
+
+impl LevelFilterHandle {
+    /// Get the current level
+    pub fn level(&self) -> FilterLevel {
+        // Only valid `FilterLevel`s are ever stored
+        FilterLevel::from_usize(self.0.load(Ordering::Relaxed))
+            .unwrap_or(FilterLevel::Off)
+    }
+
+    /// Change the level
+    ///
+    /// Takes effect for all subsequent log calls, on all threads.
+    pub fn set_level(&self, level: FilterLevel) {
+        self.0.store(level.as_usize(), Ordering::Relaxed);
+    }
+}
+
 /// `Drain` mapping error returned by another `Drain`
 ///
 /// See `Drain::map_err` for convenience function.
@@ -1915,7 +2046,7 @@ impl<D: Drain, E> Drain for MapError<D, E> {
 
 /// `Drain` duplicating records into two other `Drain`s
 ///
-/// Can be nested for more than two outputs.
+/// Can be nested for more than two outputs, but see `Fanout` for that.
 #[derive(Debug, Clone)]
 #[must_use = "does nothing by itself; needs to be attached to Logger"]
 pub struct Duplicate<D1: Drain, D2: Drain>(pub D1, pub D2);
@@ -2684,6 +2815,24 @@ impl<'a> Record<'a> {
     pub fn kv(&self) -> BorrowedKV<'_> {
         BorrowedKV(self.kv.0)
     }
+
+    /// Call `f` with a copy of this record at a different `level`
+    ///
+    /// `Record` borrows a `RecordStatic` holding its level, so the level can
+    /// not be changed in place. This builds the new `RecordStatic` on the
+    /// stack, so drains can pass records on under another level without
+    /// allocating.
+    pub fn with_level<F, R>(&self, level: Level, f: F) -> R
+    where
+        F: FnOnce(&Record<'_>) -> R,
+    {
+        let rs = RecordStatic {
+            location: self.rstatic.location,
+            level,
+            tag: self.rstatic.tag,
+        };
+        f(&Record::new(&rs, self.msg, BorrowedKV(self.kv.0)))
+    }
 }
 // }}}
 
@@ -0,0 +1,368 @@
+//! Sending `log` records to slog
+//!
+//! See [`LogBridge`].
+
+use alloc::boxed::Box;
+#[cfg(feature = "dynamic-keys")]
+use alloc::string::ToString;
+use core::result;
+
+use log::kv::{Source, VisitSource, VisitValue};
+
+use crate::{
+    BorrowedKV, Drain, Key, Level, Logger, Record, RecordLocation,
+    RecordStatic, Serializer, KV,
+};
+
+fn from_log_level(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::Error,
+        log::Level::Warn => Level::Warning,
+        log::Level::Info => Level::Info,
+        log::Level::Debug => Level::Debug,
+        log::Level::Trace => Level::Trace,
+    }
+}
+
+/// `log::Log` sending `log` records to a `Logger`
+///
+/// Records are built at runtime: the `target` of a `log` record becomes its
+/// module, and its key-value pairs become the key-value pairs of the slog
+/// record. `log` levels map to slog levels of the same name, with `Warn`
+/// becoming `Warning`.
+///
+/// Targets and files of `log` records are not always `'static`, so they
+/// are interned process-wide: each distinct one is allocated once, and kept
+/// for the lifetime of the program. So are keys, unless the `dynamic-keys`
+/// feature is enabled. Once 4096 strings are interned, a record whose
+/// target would need a new one gets `log` as its module, and a `target`
+/// key-value pair with its target, and a record whose file would need a
+/// new one gets an empty file. Key-value pairs whose key would need a new
+/// one are logged as a `kv` key-value pair, with `key=value` text.
+/// This budget is shared with the `MapKeys` drains of the process, so they
+/// can use it up for `LogBridge`, and the other way around.
+///
+/// ```
+/// use slog::{o, Discard, LogBridge, Logger};
+///
+/// let log = Logger::root(Discard, o!());
+/// LogBridge::new(log).init().unwrap();
+/// log::info!(target: "dependency", count = 3; "logged to slog");
+/// ```
+///
+/// Requires the `log` feature, and is not available with the `nothreads`
+/// feature, since `log::Log` must be `Send` and `Sync`.
+pub struct LogBridge {
+    logger: Logger,
+    /// `'static` copy of a target, file or key, if one may still be
+    /// allocated
+    intern: fn(&str) -> Option<&'static str>,
+}
+
+impl LogBridge {
+    /// Create `LogBridge` logging to `logger`
+    pub fn new(logger: Logger) -> Self {
+        LogBridge {
+            logger,
+            intern: crate::intern::intern,
+        }
+    }
+
+    /// Install as the `log` logger
+    ///
+    /// Also sets the maximum level of `log` to the most verbose level
+    /// enabled by the `Logger`, so that disabled records are skipped early.
+    pub fn init(self) -> result::Result<(), log::SetLoggerError> {
+        let max_level = [
+            log::Level::Trace,
+            log::Level::Debug,
+            log::Level::Info,
+            log::Level::Warn,
+            log::Level::Error,
+        ]
+        .iter()
+        .find(|&&level| self.logger.is_enabled(from_log_level(level)))
+        .map_or(log::LevelFilter::Off, |level| level.to_level_filter());
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.logger.is_enabled(from_log_level(metadata.level()))
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let (module, target) = match record.module_path_static() {
+            Some(module) if module == record.target() => (module, None),
+            _ => match (self.intern)(record.target()) {
+                Some(module) => (module, None),
+                None => ("log", Some(record.target())),
+            },
+        };
+        let file = match (record.file_static(), record.file()) {
+            (Some(file), _) => file,
+            (None, Some(file)) => (self.intern)(file).unwrap_or(""),
+            (None, None) => "",
+        };
+        let location = RecordLocation {
+            file,
+            line: record.line().unwrap_or(0),
+            column: 0,
+            function: "",
+            module,
+        };
+        let rs = RecordStatic {
+            location: &location,
+            tag: "",
+            level: from_log_level(record.level()),
+        };
+        let kv = LogKV {
+            source: record.key_values(),
+            target,
+            intern: self.intern,
+        };
+        self.logger
+            .log(&Record::new(&rs, record.args(), BorrowedKV(&kv)));
+    }
+
+    fn flush(&self) {
+        let _ = self.logger.flush();
+    }
+}
+
+#[cfg(feature = "dynamic-keys")]
+fn to_key(key: &str, _intern: fn(&str) -> Option<&'static str>) -> Option<Key> {
+    Some(Key::from(key.to_string()))
+}
+
+#[cfg(not(feature = "dynamic-keys"))]
+fn to_key(key: &str, intern: fn(&str) -> Option<&'static str>) -> Option<Key> {
+    intern(key)
+}
+
+/// Key-value pairs of a `log` record
+struct LogKV<'a> {
+    source: &'a dyn Source,
+    /// Target that could not be used as the module
+    target: Option<&'a str>,
+    intern: fn(&str) -> Option<&'static str>,
+}
+
+impl KV for LogKV<'_> {
+    fn serialize(
+        &self,
+        _record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        let mut visitor = PairVisitor {
+            serializer: &mut *serializer,
+            intern: self.intern,
+            result: Ok(()),
+        };
+        let _ = self.source.visit(&mut visitor);
+        visitor.result?;
+        if let Some(target) = self.target {
+            serializer.emit_str(Key::from("target"), target)?;
+        }
+        Ok(())
+    }
+}
+
+struct PairVisitor<'a> {
+    serializer: &'a mut dyn Serializer,
+    intern: fn(&str) -> Option<&'static str>,
+    /// Error of the `Serializer`, which `log` errors can not carry
+    result: crate::Result,
+}
+
+impl<'kvs> VisitSource<'kvs> for PairVisitor<'_> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> result::Result<(), log::kv::Error> {
+        let slog_key = match to_key(key.as_str(), self.intern) {
+            Some(slog_key) => slog_key,
+            None => {
+                self.result = self.serializer.emit_arguments(
+                    Key::from("kv"),
+                    &format_args!("{}={}", key, value),
+                );
+                return to_log_result(&self.result);
+            }
+        };
+        let mut visitor = ValueVisitor {
+            serializer: &mut *self.serializer,
+            key: Some(slog_key),
+            result: Ok(()),
+        };
+        let visited = value.visit(&mut visitor);
+        self.result = visitor.result;
+        visited
+    }
+}
+
+/// Emits a `log` value with the matching `Serializer` method
+struct ValueVisitor<'a> {
+    serializer: &'a mut dyn Serializer,
+    /// Taken by the one `emit_*` call
+    key: Option<Key>,
+    result: crate::Result,
+}
+
+impl ValueVisitor<'_> {
+    fn key(&mut self) -> Key {
+        self.key.take().unwrap_or_default()
+    }
+
+    fn check(
+        &mut self,
+        result: crate::Result,
+    ) -> result::Result<(), log::kv::Error> {
+        self.result = result;
+        to_log_result(&self.result)
+    }
+}
+
+/// `log` error for a `Serializer` error, kept apart
+fn to_log_result(result: &crate::Result) -> result::Result<(), log::kv::Error> {
+    result
+        .as_ref()
+        .map(|_| ())
+        .map_err(|_| log::kv::Error::msg("slog serializer failed"))
+}
+
+macro_rules! visit_emit {
+    ($($visit:ident($ty:ty) => $emit:ident),* $(,)?) => {$(
+        fn $visit(
+            &mut self,
+            value: $ty,
+        ) -> result::Result<(), log::kv::Error> {
+            let key = self.key();
+            let result = self.serializer.$emit(key, value);
+            self.check(result)
+        }
+    )*};
+}
+
+impl<'v> VisitValue<'v> for ValueVisitor<'_> {
+    fn visit_any(
+        &mut self,
+        value: log::kv::Value<'_>,
+    ) -> result::Result<(), log::kv::Error> {
+        let key = self.key();
+        let result = self
+            .serializer
+            .emit_arguments(key, &format_args!("{}", value));
+        self.check(result)
+    }
+
+    fn visit_null(&mut self) -> result::Result<(), log::kv::Error> {
+        let key = self.key();
+        let result = self.serializer.emit_none(key);
+        self.check(result)
+    }
+
+    visit_emit!(
+        visit_u64(u64) => emit_u64,
+        visit_i64(i64) => emit_i64,
+        visit_u128(u128) => emit_u128,
+        visit_i128(i128) => emit_i128,
+        visit_f64(f64) => emit_f64,
+        visit_bool(bool) => emit_bool,
+        visit_str(&str) => emit_str,
+        visit_char(char) => emit_char,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CaptureDrain;
+    use crate::{o, OwnedValue};
+    use log::kv::Value;
+    use log::Log;
+
+    #[test]
+    fn bridges_log_records() {
+        let capture = CaptureDrain::new();
+        let bridge =
+            LogBridge::new(Logger::root(capture.clone(), o!("root" => 1)));
+        let kvs = [
+            ("user", Value::from("alice")),
+            ("count", Value::from(3u64)),
+            ("ratio", Value::from(0.5)),
+            ("missing", Value::null()),
+        ];
+        bridge.log(
+            &log::Record::builder()
+                .level(log::Level::Warn)
+                .target("dependency::net")
+                .args(format_args!("retrying {}", "request"))
+                .module_path_static(Some("dependency::net::client"))
+                .line(Some(7))
+                .key_values(&kvs)
+                .build(),
+        );
+
+        let records = capture.take();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.level(), Level::Warning);
+        assert_eq!(record.module(), "dependency::net");
+        assert_eq!(record.line(), 7);
+        assert_eq!(record.msg(), "retrying request");
+        assert_eq!(
+            record.get("user"),
+            Some(&OwnedValue::Str("alice".to_string()))
+        );
+        assert_eq!(record.get("count"), Some(&OwnedValue::U64(3)));
+        assert_eq!(record.get("ratio"), Some(&OwnedValue::F64(0.5)));
+        assert_eq!(record.get("missing"), Some(&OwnedValue::None));
+        assert_eq!(record.get("root"), Some(&OwnedValue::I32(1)));
+    }
+
+    #[test]
+    fn falls_back_once_interning_is_exhausted() {
+        let capture = CaptureDrain::new();
+        let bridge = LogBridge {
+            logger: Logger::root(capture.clone(), o!()),
+            intern: |_| None,
+        };
+        let kvs = [("user", Value::from("alice"))];
+        bridge.log(
+            &log::Record::builder()
+                .target("dependency::net")
+                .args(format_args!("exhausted"))
+                .file(Some(&String::from("src/net.rs")))
+                .key_values(&kvs)
+                .build(),
+        );
+
+        let records = capture.take();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.module(), "log");
+        assert_eq!(record.file(), "");
+        assert_eq!(
+            record.get("target"),
+            Some(&OwnedValue::Str("dependency::net".to_string()))
+        );
+        #[cfg(not(feature = "dynamic-keys"))]
+        assert_eq!(
+            record.get("kv"),
+            Some(&OwnedValue::Arguments("user=alice".to_string()))
+        );
+        #[cfg(feature = "dynamic-keys")]
+        assert_eq!(
+            record.get("user"),
+            Some(&OwnedValue::Str("alice".to_string()))
+        );
+    }
+}
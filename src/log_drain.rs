@@ -0,0 +1,206 @@
+//! Sending slog records to the `log` facade
+//!
+//! See [`LogDrain`].
+
+use alloc::vec::Vec;
+use core::result;
+
+use log::kv::{Source, VisitSource};
+
+use crate::owned::CaptureSerializer;
+use crate::{
+    Drain, FlushError, Key, Level, Never, OwnedKVList, OwnedValue, Record, KV,
+};
+
+fn to_log_level(level: Level) -> log::Level {
+    match level {
+        Level::Critical | Level::Error => log::Level::Error,
+        Level::Warning => log::Level::Warn,
+        Level::Info => log::Level::Info,
+        Level::Debug => log::Level::Debug,
+        Level::Trace => log::Level::Trace,
+    }
+}
+
+/// `Drain` sending records to the `log` facade
+///
+/// The module of a record becomes the `target` of the `log` record, and its
+/// key-value pairs, followed by the ones of its `Logger`, become the
+/// key-value pairs of the `log` record. `Critical` records are logged at
+/// `log::Level::Error`, and `Warning` ones at `log::Level::Warn`.
+///
+/// ```
+/// use slog::{o, LogDrain, Logger};
+///
+/// let log = Logger::root(LogDrain::new(), o!("service" => "api"));
+/// slog::info!(log, "logged to the log facade"; "count" => 3);
+/// ```
+///
+/// Requires the `log` feature.
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogDrain {
+    _private: (),
+}
+
+impl LogDrain {
+    /// Create `LogDrain`
+    pub fn new() -> Self {
+        LogDrain::default()
+    }
+}
+
+impl Drain for LogDrain {
+    type Ok = ();
+    type Err = Never;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        values: &OwnedKVList,
+    ) -> result::Result<(), Never> {
+        let level = to_log_level(record.level());
+        if level > log::max_level() {
+            return Ok(());
+        }
+        let metadata = log::Metadata::builder()
+            .level(level)
+            .target(record.module())
+            .build();
+        let logger = log::logger();
+        if !logger.enabled(&metadata) {
+            return Ok(());
+        }
+        let mut pairs = Vec::new();
+        let mut serializer = CaptureSerializer::new(&mut pairs);
+        let _ = record.kv().serialize(record, &mut serializer);
+        let _ = values.serialize(record, &mut serializer);
+        logger.log(
+            &log::Record::builder()
+                .metadata(metadata)
+                .args(*record.msg())
+                .module_path_static(Some(record.module()))
+                .file_static(Some(record.file()))
+                .line(Some(record.line()))
+                .key_values(&Pairs(&pairs))
+                .build(),
+        );
+        Ok(())
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        to_log_level(level) <= log::max_level()
+    }
+    fn flush(&self) -> result::Result<(), FlushError> {
+        log::logger().flush();
+        Ok(())
+    }
+}
+
+/// Captured key-value pairs, as a `log` key-value `Source`
+struct Pairs<'a>(&'a [(Key, OwnedValue)]);
+
+impl Source for Pairs<'_> {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn VisitSource<'kvs>,
+    ) -> result::Result<(), log::kv::Error> {
+        for (key, value) in self.0 {
+            visitor.visit_pair(
+                log::kv::Key::from_str(AsRef::<str>::as_ref(key)),
+                to_log_value(value),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn to_log_value(value: &OwnedValue) -> log::kv::Value<'_> {
+    use log::kv::Value;
+    match *value {
+        OwnedValue::Usize(v) => Value::from(v),
+        OwnedValue::Isize(v) => Value::from(v),
+        OwnedValue::Bool(v) => Value::from(v),
+        OwnedValue::Char(v) => Value::from(v),
+        OwnedValue::U8(v) => Value::from(v),
+        OwnedValue::I8(v) => Value::from(v),
+        OwnedValue::U16(v) => Value::from(v),
+        OwnedValue::I16(v) => Value::from(v),
+        OwnedValue::U32(v) => Value::from(v),
+        OwnedValue::I32(v) => Value::from(v),
+        OwnedValue::F32(v) => Value::from(v),
+        OwnedValue::U64(v) => Value::from(v),
+        OwnedValue::I64(v) => Value::from(v),
+        OwnedValue::F64(v) => Value::from(v),
+        OwnedValue::U128(v) => Value::from(v),
+        OwnedValue::I128(v) => Value::from(v),
+        OwnedValue::Str(ref s) => Value::from(s.as_str()),
+        OwnedValue::Unit | OwnedValue::None => Value::null(),
+        _ => Value::from_dyn_display(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger};
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::string::String;
+    use alloc::sync::Arc;
+    use std::sync::Mutex;
+
+    /// Formats `log` records
+    struct CaptureLog(Arc<Mutex<Vec<String>>>);
+
+    impl log::Log for CaptureLog {
+        fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record<'_>) {
+            struct Collect(String);
+            impl<'kvs> VisitSource<'kvs> for Collect {
+                fn visit_pair(
+                    &mut self,
+                    key: log::kv::Key<'kvs>,
+                    value: log::kv::Value<'kvs>,
+                ) -> result::Result<(), log::kv::Error> {
+                    self.0 += &format!(" {}={}", key, value);
+                    Ok(())
+                }
+            }
+            let mut kv = Collect(String::new());
+            record.key_values().visit(&mut kv).unwrap();
+            self.0.lock().unwrap().push(format!(
+                "{} {} {}{}",
+                record.level(),
+                record.target(),
+                record.args(),
+                kv.0
+            ));
+        }
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn drains_to_log() {
+        // The only test installing a `log` logger
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        log::set_boxed_logger(Box::new(CaptureLog(captured.clone()))).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let log = Logger::root(LogDrain::new(), o!("service" => "api"));
+        crate::crit!(log, "down"; "code" => 5, "reason" => %"oom");
+        crate::warn!(log, "slow");
+        crate::trace!(log, "skipped");
+        assert!(!LogDrain::new().is_enabled(Level::Trace));
+
+        assert_eq!(
+            *captured.lock().unwrap(),
+            [
+                "ERROR slog::log_drain::tests down reason=oom code=5 \
+                 service=api",
+                "WARN slog::log_drain::tests slow service=api",
+            ]
+        );
+    }
+}
@@ -0,0 +1,489 @@
+//! Renaming keys
+//!
+//! See [`MapKeys`].
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::{fmt, result};
+use std::collections::HashMap;
+#[cfg(not(feature = "dynamic-keys"))]
+use std::sync::{PoisonError, RwLock};
+
+use crate::{
+    BorrowedKV, BytesKind, Drain, FlushError, Key, Level, OwnedKV, OwnedKVList,
+    Record, RecordStatic, Serializer, KV,
+};
+
+/// Case convention `MapKeys` converts keys to
+///
+/// Words are delimited by `_`, `-`, spaces and case changes. Dots are kept,
+/// and each dot-separated part is converted separately, so `http.statusCode`
+/// becomes `http.status_code` in `KeyCase::Snake`.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyCase {
+    /// `snake_case`
+    Snake,
+    /// `kebab-case`
+    Kebab,
+    /// `camelCase`
+    Camel,
+    /// `PascalCase`
+    Pascal,
+}
+
+impl KeyCase {
+    fn convert(self, key: &str) -> String {
+        let mut out = String::with_capacity(key.len() + 4);
+        for (i, part) in key.split('.').enumerate() {
+            if i > 0 {
+                out.push('.');
+            }
+            for (j, word) in words(part).iter().enumerate() {
+                match self {
+                    KeyCase::Snake | KeyCase::Kebab => {
+                        if j > 0 {
+                            out.push(if self == KeyCase::Snake {
+                                '_'
+                            } else {
+                                '-'
+                            });
+                        }
+                        out.extend(word.chars().flat_map(char::to_lowercase));
+                    }
+                    KeyCase::Camel | KeyCase::Pascal => {
+                        let mut chars = word.chars();
+                        if let Some(first) = chars.next() {
+                            if j == 0 && self == KeyCase::Camel {
+                                out.extend(first.to_lowercase());
+                            } else {
+                                out.extend(first.to_uppercase());
+                            }
+                        }
+                        out.extend(chars.flat_map(char::to_lowercase));
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Split `s` into words, at separators and case changes
+fn words(s: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut chars = s.char_indices().peekable();
+    let mut prev: Option<char> = None;
+    while let Some((i, c)) = chars.next() {
+        if c == '_' || c == '-' || c == ' ' {
+            if let Some(start) = start.take() {
+                words.push(&s[start..i]);
+            }
+            prev = None;
+            continue;
+        }
+        let boundary = match prev {
+            // `aB` and `1B`
+            Some(p) if c.is_uppercase() && !p.is_uppercase() => true,
+            // The `S` of `HTTPServer`
+            Some(p) if p.is_uppercase() && c.is_uppercase() => {
+                chars.peek().map_or(false, |&(_, next)| next.is_lowercase())
+            }
+            _ => false,
+        };
+        match start {
+            Some(from) if boundary => {
+                words.push(&s[from..i]);
+                start = Some(i);
+            }
+            None => start = Some(i),
+            Some(_) => {}
+        }
+        prev = Some(c);
+    }
+    if let Some(start) = start {
+        words.push(&s[start..]);
+    }
+    words
+}
+
+struct Mapper {
+    table: HashMap<String, String>,
+    case: Option<KeyCase>,
+    prefix: String,
+    /// Rewritten keys, or `None` if the key is kept
+    #[cfg(not(feature = "dynamic-keys"))]
+    cache: RwLock<HashMap<&'static str, Option<&'static str>>>,
+}
+
+impl Mapper {
+    fn is_empty(&self) -> bool {
+        self.table.is_empty() && self.case.is_none() && self.prefix.is_empty()
+    }
+
+    /// Get the new name of `name`, or `None` if it is kept
+    fn rewrite(&self, name: &str) -> Option<String> {
+        if let Some(mapped) = self.table.get(name) {
+            return Some(mapped.clone());
+        }
+        let mut mapped = self.prefix.clone();
+        match self.case {
+            Some(case) => mapped.push_str(&case.convert(name)),
+            None => mapped.push_str(name),
+        }
+        if mapped == name {
+            None
+        } else {
+            Some(mapped)
+        }
+    }
+
+    #[cfg(feature = "dynamic-keys")]
+    fn map(&self, key: Key) -> Key {
+        if self.is_empty() {
+            return key;
+        }
+        match self.rewrite(AsRef::<str>::as_ref(&key)) {
+            Some(mapped) => Key::from(mapped),
+            None => key,
+        }
+    }
+
+    #[cfg(not(feature = "dynamic-keys"))]
+    fn map(&self, key: Key) -> Key {
+        if self.is_empty() {
+            return key;
+        }
+        let cached = self
+            .cache
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .copied();
+        let mapped = match cached {
+            Some(mapped) => mapped,
+            None => {
+                let mapped = self
+                    .rewrite(key)
+                    .and_then(|mapped| crate::intern::intern(&mapped));
+                self.cache
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(key, mapped);
+                mapped
+            }
+        };
+        mapped.unwrap_or(key)
+    }
+}
+
+/// `Drain` renaming the keys of records and their loggers
+///
+/// A key is renamed according to:
+///
+/// 1. the mapping table, if it has an entry for the key, which is used
+///    as is,
+/// 2. otherwise, the case conversion, if any, followed by the prefix, if
+///    any.
+///
+/// Keys are `&'static str` unless the `dynamic-keys` feature is enabled,
+/// so without it, rewritten keys are interned process-wide: each distinct
+/// rewritten key is allocated once, and kept for the lifetime of the
+/// program. Once 4096 strings are interned, keys that would need a new one
+/// are kept as they are. This budget is shared by all `MapKeys` drains and
+/// the `LogBridge` of the process, so one of them can use it up for the
+/// others. With `dynamic-keys`, rewritten keys are owned, and nothing is
+/// kept.
+///
+/// ```
+/// use slog::{o, Discard, Drain, KeyCase, MapKeys};
+///
+/// let drain = MapKeys::new(Discard)
+///     .rename("method", "http.request.method")
+///     .case(KeyCase::Camel)
+///     .prefix("acme.");
+/// let log = slog::Logger::root(drain, o!("user_id" => 5));
+/// // Logged with `http.request.method` and `acme.userId`
+/// slog::info!(log, "request"; "method" => "GET");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct MapKeys<D: Drain> {
+    drain: D,
+    mapper: Arc<Mapper>,
+}
+
+impl<D: Drain> MapKeys<D> {
+    /// Create `MapKeys`, without any rules
+    pub fn new(drain: D) -> Self {
+        MapKeys {
+            drain,
+            mapper: Arc::new(Mapper {
+                table: HashMap::new(),
+                case: None,
+                prefix: String::new(),
+                #[cfg(not(feature = "dynamic-keys"))]
+                cache: RwLock::new(HashMap::new()),
+            }),
+        }
+    }
+
+    fn mapper_mut(&mut self) -> &mut Mapper {
+        let mapper = Arc::get_mut(&mut self.mapper)
+            .expect("logger values hold on to the rules only while logging");
+        // Rules changed, so previously rewritten keys may be wrong
+        #[cfg(not(feature = "dynamic-keys"))]
+        mapper
+            .cache
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        mapper
+    }
+
+    /// Rename `from` to `to`, without applying the case conversion and
+    /// prefix
+    pub fn rename(mut self, from: &str, to: &str) -> Self {
+        self.mapper_mut()
+            .table
+            .insert(from.to_string(), to.to_string());
+        self
+    }
+
+    /// Convert all other keys to `case`
+    pub fn case(mut self, case: KeyCase) -> Self {
+        self.mapper_mut().case = Some(case);
+        self
+    }
+
+    /// Prefix all other keys with `prefix`, after the case conversion
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.mapper_mut().prefix = prefix.to_string();
+        self
+    }
+}
+
+impl<D: Drain> Drain for MapKeys<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        let rs = RecordStatic {
+            location: record.location(),
+            level: record.level(),
+            tag: record.tag(),
+        };
+        let kv = MapKV {
+            kv: record.kv().0,
+            mapper: &self.mapper,
+        };
+        let values = OwnedKVList::from(OwnedKV(MapValues {
+            values: logger_values.clone(),
+            mapper: self.mapper.clone(),
+        }));
+        self.drain
+            .log(&Record::new(&rs, record.msg(), BorrowedKV(&kv)), &values)
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+struct MapKV<'a> {
+    kv: &'a dyn KV,
+    mapper: &'a Mapper,
+}
+
+impl KV for MapKV<'_> {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        self.kv.serialize(
+            record,
+            &mut MapSerializer {
+                inner: serializer,
+                mapper: self.mapper,
+            },
+        )
+    }
+}
+
+struct MapValues {
+    values: OwnedKVList,
+    mapper: Arc<Mapper>,
+}
+
+impl KV for MapValues {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        self.values.serialize(
+            record,
+            &mut MapSerializer {
+                inner: serializer,
+                mapper: &self.mapper,
+            },
+        )
+    }
+}
+
+struct MapSerializer<'a> {
+    inner: &'a mut dyn Serializer,
+    mapper: &'a Mapper,
+}
+
+macro_rules! map_emit {
+    ($($name:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {$(
+        fn $name(&mut self, key: Key $(, $arg: $ty)*) -> crate::Result {
+            self.inner.$name(self.mapper.map(key) $(, $arg)*)
+        }
+    )*};
+}
+
+impl Serializer for MapSerializer<'_> {
+    map_emit!(
+        emit_usize(val: usize),
+        emit_isize(val: isize),
+        emit_bool(val: bool),
+        emit_char(val: char),
+        emit_u8(val: u8),
+        emit_i8(val: i8),
+        emit_u16(val: u16),
+        emit_i16(val: i16),
+        emit_u32(val: u32),
+        emit_i32(val: i32),
+        emit_f32(val: f32),
+        emit_u64(val: u64),
+        emit_i64(val: i64),
+        emit_f64(val: f64),
+        emit_u128(val: u128),
+        emit_i128(val: i128),
+        emit_str(val: &str),
+        emit_unit(),
+        emit_none(),
+        emit_bytes(bytes: &[u8], kind: BytesKind),
+        emit_arguments(val: &fmt::Arguments<'_>),
+    );
+
+    #[cfg(has_std_error)]
+    map_emit!(emit_error(error: &(dyn crate::StdError + 'static)));
+
+    #[cfg(feature = "nested-values")]
+    map_emit!(emit_serde(value: &dyn crate::SerdeValue));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use alloc::string::String;
+    use std::sync::Mutex;
+
+    /// Collects keys
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            values: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            let mut keys = Vec::new();
+            let mut ser = crate::AsFmtSerializer(|key, _| {
+                keys.push(key.to_string());
+                Ok(())
+            });
+            record.kv().serialize(record, &mut ser).unwrap();
+            values.serialize(record, &mut ser).unwrap();
+            self.0.lock().unwrap().push(keys.join(" "));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn converts_case() {
+        let convert = |case: KeyCase| {
+            [
+                "user_id",
+                "userId",
+                "HTTPServer",
+                "http.statusCode",
+                "a-b c",
+            ]
+            .iter()
+            .map(|key| case.convert(key))
+            .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            convert(KeyCase::Snake),
+            [
+                "user_id",
+                "user_id",
+                "http_server",
+                "http.status_code",
+                "a_b_c"
+            ]
+        );
+        assert_eq!(
+            convert(KeyCase::Kebab),
+            [
+                "user-id",
+                "user-id",
+                "http-server",
+                "http.status-code",
+                "a-b-c"
+            ]
+        );
+        assert_eq!(
+            convert(KeyCase::Camel),
+            ["userId", "userId", "httpServer", "http.statusCode", "aBC"]
+        );
+        assert_eq!(
+            convert(KeyCase::Pascal),
+            ["UserId", "UserId", "HttpServer", "Http.StatusCode", "ABC"]
+        );
+    }
+
+    #[test]
+    fn renames_record_and_logger_keys() {
+        let collect = Collect::default();
+        let drain = MapKeys::new(collect.clone())
+            .rename("method", "http.request.method")
+            .case(KeyCase::Camel)
+            .prefix("acme.");
+        let log = Logger::root(drain, o!("user_id" => 5));
+        crate::info!(log, "a"; "method" => "GET", "requestId" => 1);
+        crate::info!(log, "b"; "request_id" => 2);
+        assert_eq!(
+            *collect.0.lock().unwrap(),
+            [
+                "acme.requestId http.request.method acme.userId",
+                "acme.requestId acme.userId",
+            ]
+        );
+
+        let unchanged = Collect::default();
+        let log = Logger::root(
+            MapKeys::new(unchanged.clone()).case(KeyCase::Snake),
+            o!(),
+        );
+        crate::info!(log, "a"; "user_id" => 5);
+        assert_eq!(*unchanged.0.lock().unwrap(), ["user_id"]);
+    }
+}
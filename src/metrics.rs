@@ -0,0 +1,472 @@
+//! Counting records and timing drains
+//!
+//! See [`Metrics`].
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use core::convert::TryFrom;
+use core::fmt::{self, Write as _};
+use core::result;
+use core::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use crate::{Drain, FlushError, Level, OwnedKVList, Record};
+
+/// Timings of calls to a drain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Latency {
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Latency {
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    /// Number of calls
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Total time spent in the calls
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Longest call
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Average call time, or zero if there were no calls
+    pub fn mean(&self) -> Duration {
+        // `Duration / u64` is not available, but counts fit in `u32` in
+        // practice
+        let count = u32::try_from(self.count).unwrap_or(u32::MAX);
+        self.total.checked_div(count).unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    /// Indexed by `Level::as_usize() - 1`
+    levels: [u64; 6],
+    modules: HashMap<&'static str, u64>,
+    tags: HashMap<String, u64>,
+    log_errors: u64,
+    flush_errors: u64,
+    log_latency: Latency,
+    flush_latency: Latency,
+}
+
+/// `Drain` counting the records it logs, and timing the drain it wraps
+///
+/// Counts records by level, module and tag, and errors returned by the
+/// wrapped drain, and measures how long its `log` and `flush` calls take.
+/// Use `Metrics::handle` to get a snapshot of the metrics, e.g. to render
+/// them in the Prometheus text format.
+///
+/// Wrapping several parts of a drain composition in differently named
+/// `Metrics` shows how much time each of them takes.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Level, Metrics};
+///
+/// let metrics = Metrics::new(Discard).name("discard");
+/// let handle = metrics.handle();
+/// let log = slog::Logger::root(metrics.fuse(), o!());
+/// slog::warn!(log, "disk almost full");
+///
+/// let snapshot = handle.snapshot();
+/// assert_eq!(snapshot.count(Level::Warning), 1);
+/// assert!(snapshot
+///     .to_prometheus()
+///     .contains("slog_records_total{drain=\"discard\",level=\"WARN\"} 1"));
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Metrics<D: Drain> {
+    drain: D,
+    handle: MetricsHandle,
+}
+
+impl<D: Drain> Metrics<D> {
+    /// Create `Metrics`, with all counters at zero
+    pub fn new(drain: D) -> Self {
+        Metrics {
+            drain,
+            handle: MetricsHandle {
+                name: None,
+                counters: Arc::new(Mutex::new(Counters::default())),
+            },
+        }
+    }
+
+    /// Set a name for this drain, added as a `drain` label in the
+    /// Prometheus text format
+    pub fn name(mut self, name: &str) -> Self {
+        self.handle.name = Some(name.into());
+        self
+    }
+
+    /// Get a handle to read the metrics
+    pub fn handle(&self) -> MetricsHandle {
+        self.handle.clone()
+    }
+}
+
+impl<D: Drain> Drain for Metrics<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        let start = Instant::now();
+        let res = self.drain.log(record, logger_values);
+        let elapsed = start.elapsed();
+
+        let mut counters = self.handle.lock();
+        counters.levels[record.level().as_usize() - 1] += 1;
+        *counters.modules.entry(record.module()).or_insert(0) += 1;
+        match counters.tags.get_mut(record.tag()) {
+            Some(count) => *count += 1,
+            None => {
+                counters.tags.insert(record.tag().to_string(), 1);
+            }
+        }
+        if res.is_err() {
+            counters.log_errors += 1;
+        }
+        counters.log_latency.record(elapsed);
+        res
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    /// Flush the wrapped drain, timing it
+    ///
+    /// `FlushError::NotSupported` is not counted as an error.
+    fn flush(&self) -> result::Result<(), FlushError> {
+        let start = Instant::now();
+        let res = self.drain.flush();
+        let elapsed = start.elapsed();
+
+        let mut counters = self.handle.lock();
+        if let Err(ref e) = res {
+            if !matches!(e, FlushError::NotSupported) {
+                counters.flush_errors += 1;
+            }
+        }
+        counters.flush_latency.record(elapsed);
+        res
+    }
+}
+
+/// Handle reading the metrics of a `Metrics` drain
+///
+/// All clones read the same metrics.
+#[derive(Clone)]
+pub struct MetricsHandle {
+    name: Option<String>,
+    counters: Arc<Mutex<Counters>>,
+}
+
+impl MetricsHandle {
+    fn lock(&self) -> MutexGuard<'_, Counters> {
+        // Counters are updated in one go, so they are consistent anyway
+        self.counters.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get a copy of the current metrics
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let counters = self.lock();
+        MetricsSnapshot {
+            name: self.name.clone(),
+            levels: counters.levels,
+            modules: counters
+                .modules
+                .iter()
+                .map(|(module, count)| (module.to_string(), *count))
+                .collect(),
+            tags: counters
+                .tags
+                .iter()
+                .map(|(tag, count)| (tag.clone(), *count))
+                .collect(),
+            log_errors: counters.log_errors,
+            flush_errors: counters.flush_errors,
+            log_latency: counters.log_latency,
+            flush_latency: counters.flush_latency,
+        }
+    }
+
+    /// Reset all the metrics to zero
+    pub fn reset(&self) {
+        *self.lock() = Counters::default();
+    }
+}
+
+impl fmt::Debug for MetricsHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MetricsHandle")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// Metrics of a `Metrics` drain at some point in time
+///
+/// See `MetricsHandle::snapshot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    name: Option<String>,
+    levels: [u64; 6],
+    modules: BTreeMap<String, u64>,
+    tags: BTreeMap<String, u64>,
+    log_errors: u64,
+    flush_errors: u64,
+    log_latency: Latency,
+    flush_latency: Latency,
+}
+
+impl MetricsSnapshot {
+    /// Number of records logged at `level`
+    pub fn count(&self, level: Level) -> u64 {
+        self.levels[level.as_usize() - 1]
+    }
+
+    /// Number of records logged at any level
+    pub fn total(&self) -> u64 {
+        self.levels.iter().sum()
+    }
+
+    /// Number of records logged, by module
+    pub fn by_module(&self) -> &BTreeMap<String, u64> {
+        &self.modules
+    }
+
+    /// Number of records logged, by tag
+    ///
+    /// Untagged records are counted under the empty tag.
+    pub fn by_tag(&self) -> &BTreeMap<String, u64> {
+        &self.tags
+    }
+
+    /// Number of errors returned by the wrapped drain's `log`
+    pub fn log_errors(&self) -> u64 {
+        self.log_errors
+    }
+
+    /// Number of errors returned by the wrapped drain's `flush`, other than
+    /// `FlushError::NotSupported`
+    pub fn flush_errors(&self) -> u64 {
+        self.flush_errors
+    }
+
+    /// Timings of the wrapped drain's `log`
+    pub fn log_latency(&self) -> &Latency {
+        &self.log_latency
+    }
+
+    /// Timings of the wrapped drain's `flush`
+    pub fn flush_latency(&self) -> &Latency {
+        &self.flush_latency
+    }
+
+    /// Render the metrics in the Prometheus text exposition format
+    ///
+    /// Metrics are prefixed with `slog_`, and labelled with the name given
+    /// by `Metrics::name`, if any.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut prom = Prometheus {
+            out: &mut out,
+            name: self.name.as_deref(),
+        };
+
+        prom.header("records_total", "counter", "Records logged, by level");
+        for (i, count) in self.levels.iter().enumerate() {
+            let level =
+                Level::from_usize(i + 1).map_or("", |l| l.as_short_str());
+            prom.sample("records_total", Some(("level", level)), *count);
+        }
+        prom.header(
+            "records_by_module_total",
+            "counter",
+            "Records logged, by module",
+        );
+        for (module, count) in &self.modules {
+            prom.sample(
+                "records_by_module_total",
+                Some(("module", module)),
+                *count,
+            );
+        }
+        prom.header(
+            "records_by_tag_total",
+            "counter",
+            "Records logged, by tag",
+        );
+        for (tag, count) in &self.tags {
+            prom.sample("records_by_tag_total", Some(("tag", tag)), *count);
+        }
+        prom.header("log_errors_total", "counter", "Errors returned by log");
+        prom.sample("log_errors_total", None, self.log_errors);
+        prom.header(
+            "flush_errors_total",
+            "counter",
+            "Errors returned by flush",
+        );
+        prom.sample("flush_errors_total", None, self.flush_errors);
+        prom.latency("log", &self.log_latency);
+        prom.latency("flush", &self.flush_latency);
+        out
+    }
+}
+
+/// Writer for the Prometheus text exposition format
+struct Prometheus<'a> {
+    out: &'a mut String,
+    name: Option<&'a str>,
+}
+
+impl Prometheus<'_> {
+    fn header(&mut self, metric: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP slog_{} {}.", metric, help);
+        let _ = writeln!(self.out, "# TYPE slog_{} {}", metric, kind);
+    }
+
+    fn sample<V: fmt::Display>(
+        &mut self,
+        metric: &str,
+        label: Option<(&str, &str)>,
+        value: V,
+    ) {
+        let _ = write!(self.out, "slog_{}", metric);
+        let labels = self.name.map(|name| ("drain", name)).into_iter();
+        let mut labels = labels.chain(label).peekable();
+        if labels.peek().is_some() {
+            self.out.push('{');
+            for (i, (key, value)) in labels.enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{}=\"", key);
+                for c in value.chars() {
+                    match c {
+                        '\\' => self.out.push_str("\\\\"),
+                        '"' => self.out.push_str("\\\""),
+                        '\n' => self.out.push_str("\\n"),
+                        c => self.out.push(c),
+                    }
+                }
+                self.out.push('"');
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+
+    fn latency(&mut self, call: &str, latency: &Latency) {
+        let summary = alloc::format!("{}_duration_seconds", call);
+        let help = alloc::format!("Time spent in {}", call);
+        self.header(&summary, "summary", &help);
+        let sum = alloc::format!("{}_sum", summary);
+        self.sample(&sum, None, latency.total.as_secs_f64());
+        let count = alloc::format!("{}_count", summary);
+        self.sample(&count, None, latency.count);
+
+        let max = alloc::format!("{}_duration_max_seconds", call);
+        let help = alloc::format!("Longest time spent in {}", call);
+        self.header(&max, "gauge", &help);
+        self.sample(&max, None, latency.max.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger};
+
+    /// Fails on `Error` records, and sleeps when flushed
+    struct Slow;
+
+    impl Drain for Slow {
+        type Ok = ();
+        type Err = ();
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), ()> {
+            if record.level() == Level::Error {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            std::thread::sleep(Duration::from_millis(10));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn counts_and_times() {
+        let metrics = Metrics::new(Slow);
+        let handle = metrics.handle();
+        let log = Logger::root(metrics.ignore_res(), o!());
+        crate::info!(log, "a");
+        crate::info!(log, #"audit", "b");
+        crate::error!(log, "c");
+        log.flush().unwrap();
+
+        let snapshot = handle.snapshot();
+        assert_eq!(snapshot.count(Level::Info), 2);
+        assert_eq!(snapshot.count(Level::Error), 1);
+        assert_eq!(snapshot.total(), 3);
+        assert_eq!(snapshot.by_module()[module_path!()], 3);
+        assert_eq!(snapshot.by_tag()[""], 2);
+        assert_eq!(snapshot.by_tag()["audit"], 1);
+        assert_eq!(snapshot.log_errors(), 1);
+        assert_eq!(snapshot.flush_errors(), 0);
+        assert_eq!(snapshot.log_latency().count(), 3);
+        assert_eq!(snapshot.flush_latency().count(), 1);
+        assert!(snapshot.flush_latency().max() >= Duration::from_millis(10));
+
+        handle.reset();
+        assert_eq!(handle.snapshot().total(), 0);
+    }
+
+    #[test]
+    fn prometheus_format() {
+        let metrics = Metrics::new(Slow).name("a \"b\"");
+        let handle = metrics.handle();
+        let log = Logger::root(metrics.ignore_res(), o!());
+        crate::error!(log, "c");
+
+        let text = handle.snapshot().to_prometheus();
+        assert!(text.contains(
+            "# TYPE slog_records_total counter\n\
+             slog_records_total{drain=\"a \\\"b\\\"\",level=\"CRIT\"} 0\n\
+             slog_records_total{drain=\"a \\\"b\\\"\",level=\"ERRO\"} 1\n"
+        ));
+        assert!(
+            text.contains("slog_log_errors_total{drain=\"a \\\"b\\\"\"} 1\n")
+        );
+        assert!(text.contains(
+            "# TYPE slog_flush_duration_seconds summary\n\
+             slog_flush_duration_seconds_sum{drain=\"a \\\"b\\\"\"} 0\n\
+             slog_flush_duration_seconds_count{drain=\"a \\\"b\\\"\"} 0\n"
+        ));
+    }
+}
@@ -0,0 +1,861 @@
+//! Owned, `'static` copies of logging records
+//!
+//! See [`OwnedRecord`].
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::{fmt, result};
+#[cfg(feature = "nested-values")]
+use std::sync::Mutex;
+
+#[cfg(feature = "nested-values")]
+use crate::{AsFmtSerializer, SerdeValue};
+use crate::{
+    BorrowedKV, BytesAsFmt, BytesKind, Drain, Key, Level, OwnedKV, OwnedKVList,
+    Record, RecordLocation, RecordStatic, Result, Serializer, StdError, Value,
+    KV,
+};
+
+/// Owned copy of a value emitted to a `Serializer`
+///
+/// Retains the type information of the `Serializer::emit_*` method the value
+/// was emitted with, so it can be re-emitted the same way later.
+#[non_exhaustive]
+pub enum OwnedValue {
+    /// Emitted with `emit_usize`
+    Usize(usize),
+    /// Emitted with `emit_isize`
+    Isize(isize),
+    /// Emitted with `emit_bool`
+    Bool(bool),
+    /// Emitted with `emit_char`
+    Char(char),
+    /// Emitted with `emit_u8`
+    U8(u8),
+    /// Emitted with `emit_i8`
+    I8(i8),
+    /// Emitted with `emit_u16`
+    U16(u16),
+    /// Emitted with `emit_i16`
+    I16(i16),
+    /// Emitted with `emit_u32`
+    U32(u32),
+    /// Emitted with `emit_i32`
+    I32(i32),
+    /// Emitted with `emit_f32`
+    F32(f32),
+    /// Emitted with `emit_u64`
+    U64(u64),
+    /// Emitted with `emit_i64`
+    I64(i64),
+    /// Emitted with `emit_f64`
+    F64(f64),
+    /// Emitted with `emit_u128`
+    U128(u128),
+    /// Emitted with `emit_i128`
+    I128(i128),
+    /// Emitted with `emit_str`
+    Str(String),
+    /// Emitted with `emit_arguments`, rendered to a `String`
+    Arguments(String),
+    /// Emitted with `emit_unit`
+    Unit,
+    /// Emitted with `emit_none`
+    None,
+    /// Emitted with `emit_bytes`
+    Bytes(Vec<u8>, BytesKind),
+    /// Emitted with `emit_error`
+    Error(OwnedError),
+    /// Emitted with `emit_serde`
+    #[cfg(feature = "nested-values")]
+    Serde(OwnedSerdeValue),
+}
+
+impl OwnedValue {
+    /// Capture a `Value` into an `OwnedValue`
+    ///
+    /// Returns `None` if the value did not emit anything (or failed to
+    /// serialize). If the value emitted more than once, only the first one is
+    /// kept.
+    pub fn from_value<V>(value: &V, record: &Record<'_>) -> Option<OwnedValue>
+    where
+        V: Value + ?Sized,
+    {
+        let mut pairs = Vec::new();
+        let _ = value.serialize(
+            record,
+            Key::default(),
+            &mut CaptureSerializer::new(&mut pairs),
+        );
+        pairs.into_iter().next().map(|(_, v)| v)
+    }
+
+    /// Compare by meaning rather than by how the values were emitted
+    ///
+    /// Numbers compare by value whatever their type, and strings with
+    /// formatted arguments by their text. Other values only compare to
+    /// values emitted the same way.
+    pub(crate) fn loose_cmp(&self, other: &OwnedValue) -> Option<Ordering> {
+        if let (Some(a), Some(b)) = (self.as_i128(), other.as_i128()) {
+            return Some(a.cmp(&b));
+        }
+        if let (Some(a), Some(b)) = (self.as_f64(), other.as_f64()) {
+            return a.partial_cmp(&b);
+        }
+        match (self, other) {
+            (
+                OwnedValue::Str(a) | OwnedValue::Arguments(a),
+                OwnedValue::Str(b) | OwnedValue::Arguments(b),
+            ) => Some(a.cmp(b)),
+            (OwnedValue::Bool(a), OwnedValue::Bool(b)) => Some(a.cmp(b)),
+            (OwnedValue::Char(a), OwnedValue::Char(b)) => Some(a.cmp(b)),
+            (a, b) if a == b => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+
+    /// Any integer fitting in an `i128`
+    pub(crate) fn as_i128(&self) -> Option<i128> {
+        Some(match *self {
+            OwnedValue::Usize(v) => v as i128,
+            OwnedValue::Isize(v) => v as i128,
+            OwnedValue::U8(v) => v.into(),
+            OwnedValue::I8(v) => v.into(),
+            OwnedValue::U16(v) => v.into(),
+            OwnedValue::I16(v) => v.into(),
+            OwnedValue::U32(v) => v.into(),
+            OwnedValue::I32(v) => v.into(),
+            OwnedValue::U64(v) => v.into(),
+            OwnedValue::I64(v) => v.into(),
+            OwnedValue::U128(v) => return i128::try_from(v).ok(),
+            OwnedValue::I128(v) => v,
+            _ => return None,
+        })
+    }
+
+    /// Any number, possibly losing precision
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match *self {
+            OwnedValue::F32(v) => Some(v.into()),
+            OwnedValue::F64(v) => Some(v),
+            OwnedValue::U128(v) => Some(v as f64),
+            _ => self.as_i128().map(|v| v as f64),
+        }
+    }
+}
+
+impl Clone for OwnedValue {
+    fn clone(&self) -> Self {
+        match *self {
+            OwnedValue::Usize(v) => OwnedValue::Usize(v),
+            OwnedValue::Isize(v) => OwnedValue::Isize(v),
+            OwnedValue::Bool(v) => OwnedValue::Bool(v),
+            OwnedValue::Char(v) => OwnedValue::Char(v),
+            OwnedValue::U8(v) => OwnedValue::U8(v),
+            OwnedValue::I8(v) => OwnedValue::I8(v),
+            OwnedValue::U16(v) => OwnedValue::U16(v),
+            OwnedValue::I16(v) => OwnedValue::I16(v),
+            OwnedValue::U32(v) => OwnedValue::U32(v),
+            OwnedValue::I32(v) => OwnedValue::I32(v),
+            OwnedValue::F32(v) => OwnedValue::F32(v),
+            OwnedValue::U64(v) => OwnedValue::U64(v),
+            OwnedValue::I64(v) => OwnedValue::I64(v),
+            OwnedValue::F64(v) => OwnedValue::F64(v),
+            OwnedValue::U128(v) => OwnedValue::U128(v),
+            OwnedValue::I128(v) => OwnedValue::I128(v),
+            OwnedValue::Str(ref v) => OwnedValue::Str(v.clone()),
+            OwnedValue::Arguments(ref v) => OwnedValue::Arguments(v.clone()),
+            OwnedValue::Unit => OwnedValue::Unit,
+            OwnedValue::None => OwnedValue::None,
+            OwnedValue::Bytes(ref v, kind) => {
+                OwnedValue::Bytes(v.clone(), kind)
+            }
+            OwnedValue::Error(ref v) => OwnedValue::Error(v.clone()),
+            #[cfg(feature = "nested-values")]
+            OwnedValue::Serde(ref v) => OwnedValue::Serde(v.clone()),
+        }
+    }
+}
+
+/// Compares values emitted with the same `Serializer` method
+///
+/// Nested (`emit_serde`) values never compare equal, as there is no way to
+/// compare them without serializing.
+impl PartialEq for OwnedValue {
+    fn eq(&self, other: &Self) -> bool {
+        use self::OwnedValue::*;
+        match (self, other) {
+            (Usize(a), Usize(b)) => a == b,
+            (Isize(a), Isize(b)) => a == b,
+            (Bool(a), Bool(b)) => a == b,
+            (Char(a), Char(b)) => a == b,
+            (U8(a), U8(b)) => a == b,
+            (I8(a), I8(b)) => a == b,
+            (U16(a), U16(b)) => a == b,
+            (I16(a), I16(b)) => a == b,
+            (U32(a), U32(b)) => a == b,
+            (I32(a), I32(b)) => a == b,
+            (F32(a), F32(b)) => a == b,
+            (U64(a), U64(b)) => a == b,
+            (I64(a), I64(b)) => a == b,
+            (F64(a), F64(b)) => a == b,
+            (U128(a), U128(b)) => a == b,
+            (I128(a), I128(b)) => a == b,
+            (Str(a), Str(b)) => a == b,
+            (Arguments(a), Arguments(b)) => a == b,
+            (Unit, Unit) => true,
+            (None, None) => true,
+            (Bytes(a, _), Bytes(b, _)) => a == b,
+            (Error(a), Error(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for OwnedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            OwnedValue::Usize(v) => write!(f, "Usize({:?})", v),
+            OwnedValue::Isize(v) => write!(f, "Isize({:?})", v),
+            OwnedValue::Bool(v) => write!(f, "Bool({:?})", v),
+            OwnedValue::Char(v) => write!(f, "Char({:?})", v),
+            OwnedValue::U8(v) => write!(f, "U8({:?})", v),
+            OwnedValue::I8(v) => write!(f, "I8({:?})", v),
+            OwnedValue::U16(v) => write!(f, "U16({:?})", v),
+            OwnedValue::I16(v) => write!(f, "I16({:?})", v),
+            OwnedValue::U32(v) => write!(f, "U32({:?})", v),
+            OwnedValue::I32(v) => write!(f, "I32({:?})", v),
+            OwnedValue::F32(v) => write!(f, "F32({:?})", v),
+            OwnedValue::U64(v) => write!(f, "U64({:?})", v),
+            OwnedValue::I64(v) => write!(f, "I64({:?})", v),
+            OwnedValue::F64(v) => write!(f, "F64({:?})", v),
+            OwnedValue::U128(v) => write!(f, "U128({:?})", v),
+            OwnedValue::I128(v) => write!(f, "I128({:?})", v),
+            OwnedValue::Str(ref v) => write!(f, "Str({:?})", v),
+            OwnedValue::Arguments(ref v) => write!(f, "Arguments({:?})", v),
+            OwnedValue::Unit => f.write_str("Unit"),
+            OwnedValue::None => f.write_str("None"),
+            OwnedValue::Bytes(ref v, kind) => {
+                write!(f, "Bytes({:?}, {:?})", v, kind)
+            }
+            OwnedValue::Error(ref v) => write!(f, "Error({:?})", v),
+            #[cfg(feature = "nested-values")]
+            OwnedValue::Serde(_) => f.write_str("Serde(..)"),
+        }
+    }
+}
+
+/// Formats the value the same way the default `Serializer` methods would
+impl fmt::Display for OwnedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            OwnedValue::Usize(v) => write!(f, "{}", v),
+            OwnedValue::Isize(v) => write!(f, "{}", v),
+            OwnedValue::Bool(v) => write!(f, "{}", v),
+            OwnedValue::Char(v) => write!(f, "{}", v),
+            OwnedValue::U8(v) => write!(f, "{}", v),
+            OwnedValue::I8(v) => write!(f, "{}", v),
+            OwnedValue::U16(v) => write!(f, "{}", v),
+            OwnedValue::I16(v) => write!(f, "{}", v),
+            OwnedValue::U32(v) => write!(f, "{}", v),
+            OwnedValue::I32(v) => write!(f, "{}", v),
+            OwnedValue::F32(v) => write!(f, "{}", v),
+            OwnedValue::U64(v) => write!(f, "{}", v),
+            OwnedValue::I64(v) => write!(f, "{}", v),
+            OwnedValue::F64(v) => write!(f, "{}", v),
+            OwnedValue::U128(v) => write!(f, "{}", v),
+            OwnedValue::I128(v) => write!(f, "{}", v),
+            OwnedValue::Str(ref v) | OwnedValue::Arguments(ref v) => {
+                f.write_str(v)
+            }
+            OwnedValue::Unit => f.write_str("()"),
+            OwnedValue::None => Ok(()),
+            OwnedValue::Bytes(ref bytes, kind) => {
+                write!(f, "{}", BytesAsFmt { bytes, kind })
+            }
+            OwnedValue::Error(ref v) => {
+                write!(f, "{}", crate::ErrorAsFmt(v))
+            }
+            #[cfg(feature = "nested-values")]
+            OwnedValue::Serde(ref v) => v
+                .lock()
+                .serialize_fallback(
+                    Key::default(),
+                    &mut AsFmtSerializer(|_, val| {
+                        f.write_fmt(val)?;
+                        Ok(())
+                    }),
+                )
+                .map_err(|_| fmt::Error),
+        }
+    }
+}
+
+impl Value for OwnedValue {
+    fn serialize(
+        &self,
+        _record: &Record<'_>,
+        key: Key,
+        serializer: &mut dyn Serializer,
+    ) -> Result {
+        match *self {
+            OwnedValue::Usize(v) => serializer.emit_usize(key, v),
+            OwnedValue::Isize(v) => serializer.emit_isize(key, v),
+            OwnedValue::Bool(v) => serializer.emit_bool(key, v),
+            OwnedValue::Char(v) => serializer.emit_char(key, v),
+            OwnedValue::U8(v) => serializer.emit_u8(key, v),
+            OwnedValue::I8(v) => serializer.emit_i8(key, v),
+            OwnedValue::U16(v) => serializer.emit_u16(key, v),
+            OwnedValue::I16(v) => serializer.emit_i16(key, v),
+            OwnedValue::U32(v) => serializer.emit_u32(key, v),
+            OwnedValue::I32(v) => serializer.emit_i32(key, v),
+            OwnedValue::F32(v) => serializer.emit_f32(key, v),
+            OwnedValue::U64(v) => serializer.emit_u64(key, v),
+            OwnedValue::I64(v) => serializer.emit_i64(key, v),
+            OwnedValue::F64(v) => serializer.emit_f64(key, v),
+            OwnedValue::U128(v) => serializer.emit_u128(key, v),
+            OwnedValue::I128(v) => serializer.emit_i128(key, v),
+            OwnedValue::Str(ref v) => serializer.emit_str(key, v),
+            OwnedValue::Arguments(ref v) => {
+                serializer.emit_arguments(key, &format_args!("{}", v))
+            }
+            OwnedValue::Unit => serializer.emit_unit(key),
+            OwnedValue::None => serializer.emit_none(key),
+            OwnedValue::Bytes(ref v, kind) => {
+                serializer.emit_bytes(key, v, kind)
+            }
+            OwnedValue::Error(ref v) => serializer.emit_error(key, v),
+            #[cfg(feature = "nested-values")]
+            OwnedValue::Serde(ref v) => serializer.emit_serde(key, &**v.lock()),
+        }
+    }
+}
+
+/// Owned copy of an error emitted with `Serializer::emit_error`
+///
+/// The message of the error and each of its sources is rendered to a
+/// `String`, so the chain of sources is preserved when re-emitted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OwnedError {
+    message: String,
+    source: Option<Box<OwnedError>>,
+}
+
+impl OwnedError {
+    /// Capture an error and its chain of sources
+    pub fn new(error: &(dyn StdError + 'static)) -> Self {
+        OwnedError {
+            message: error.to_string(),
+            source: error.source().map(|s| Box::new(OwnedError::new(s))),
+        }
+    }
+}
+
+impl fmt::Display for OwnedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for OwnedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_ref()
+            .map(|s| &**s as &(dyn StdError + 'static))
+    }
+}
+
+/// Owned copy of a value emitted with `Serializer::emit_serde`
+///
+/// Obtained with `SerdeValue::to_sendable`. It is kept behind a lock, so
+/// `OwnedRecord`s stay `Sync` and can be shared between threads.
+#[cfg(feature = "nested-values")]
+pub struct OwnedSerdeValue(Mutex<Box<dyn SerdeValue + Send + 'static>>);
+
+#[cfg(feature = "nested-values")]
+impl OwnedSerdeValue {
+    /// Capture a copy of `value`
+    pub fn new(value: &dyn SerdeValue) -> Self {
+        OwnedSerdeValue(Mutex::new(value.to_sendable()))
+    }
+
+    fn lock(
+        &self,
+    ) -> std::sync::MutexGuard<'_, Box<dyn SerdeValue + Send + 'static>> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl Clone for OwnedSerdeValue {
+    fn clone(&self) -> Self {
+        OwnedSerdeValue(Mutex::new(self.lock().to_sendable()))
+    }
+}
+
+#[cfg(feature = "nested-values")]
+impl Value for OwnedSerdeValue {
+    fn serialize(
+        &self,
+        _record: &Record<'_>,
+        key: Key,
+        serializer: &mut dyn Serializer,
+    ) -> Result {
+        serializer.emit_serde(key, &**self.lock())
+    }
+}
+
+/// `Serializer` capturing what it is given into `OwnedValue`s
+pub(crate) struct CaptureSerializer<'a> {
+    pairs: &'a mut Vec<(Key, OwnedValue)>,
+    /// Only keys for which this returns `true` are captured
+    filter: Option<&'a dyn Fn(&str) -> bool>,
+}
+
+impl<'a> CaptureSerializer<'a> {
+    /// Capture every key-value pair into `pairs`
+    pub(crate) fn new(pairs: &'a mut Vec<(Key, OwnedValue)>) -> Self {
+        CaptureSerializer {
+            pairs,
+            filter: None,
+        }
+    }
+
+    /// Capture the key-value pairs whose key passes `filter` into `pairs`
+    pub(crate) fn filtered(
+        pairs: &'a mut Vec<(Key, OwnedValue)>,
+        filter: &'a dyn Fn(&str) -> bool,
+    ) -> Self {
+        CaptureSerializer {
+            pairs,
+            filter: Some(filter),
+        }
+    }
+
+    fn push<F>(&mut self, key: Key, value: F) -> Result
+    where
+        F: FnOnce() -> OwnedValue,
+    {
+        if self
+            .filter
+            .map_or(true, |filter| filter(AsRef::<str>::as_ref(&key)))
+        {
+            self.pairs.push((key, value()));
+        }
+        Ok(())
+    }
+}
+
+macro_rules! impl_capture {
+    ($($f:ident($t:ty) => $variant:ident),* $(,)?) => {
+        $(
+            fn $f(&mut self, key: Key, val: $t) -> Result {
+                self.push(key, || OwnedValue::$variant(val))
+            }
+        )*
+    };
+}
+
+impl Serializer for CaptureSerializer<'_> {
+    impl_capture! {
+        emit_usize(usize) => Usize,
+        emit_isize(isize) => Isize,
+        emit_bool(bool) => Bool,
+        emit_char(char) => Char,
+        emit_u8(u8) => U8,
+        emit_i8(i8) => I8,
+        emit_u16(u16) => U16,
+        emit_i16(i16) => I16,
+        emit_u32(u32) => U32,
+        emit_i32(i32) => I32,
+        emit_f32(f32) => F32,
+        emit_u64(u64) => U64,
+        emit_i64(i64) => I64,
+        emit_f64(f64) => F64,
+        emit_u128(u128) => U128,
+        emit_i128(i128) => I128,
+    }
+
+    fn emit_str(&mut self, key: Key, val: &str) -> Result {
+        self.push(key, || OwnedValue::Str(val.into()))
+    }
+
+    fn emit_unit(&mut self, key: Key) -> Result {
+        self.push(key, || OwnedValue::Unit)
+    }
+
+    fn emit_none(&mut self, key: Key) -> Result {
+        self.push(key, || OwnedValue::None)
+    }
+
+    fn emit_bytes(
+        &mut self,
+        key: Key,
+        bytes: &[u8],
+        kind: BytesKind,
+    ) -> Result {
+        self.push(key, || OwnedValue::Bytes(bytes.into(), kind))
+    }
+
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments<'_>) -> Result {
+        self.push(key, || OwnedValue::Arguments(val.to_string()))
+    }
+
+    #[cfg(feature = "nested-values")]
+    fn emit_serde(&mut self, key: Key, value: &dyn SerdeValue) -> Result {
+        self.push(key, || OwnedValue::Serde(OwnedSerdeValue::new(value)))
+    }
+
+    fn emit_error(
+        &mut self,
+        key: Key,
+        error: &(dyn StdError + 'static),
+    ) -> Result {
+        self.push(key, || OwnedValue::Error(OwnedError::new(error)))
+    }
+}
+
+/// Borrowed list of captured key-value pairs
+struct PairsKV<'a>(&'a [(Key, OwnedValue)]);
+
+impl KV for PairsKV<'_> {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> Result {
+        for pair in self.0 {
+            #[cfg_attr(not(feature = "dynamic-keys"), allow(noop_method_call))]
+            pair.1.serialize(record, pair.0.clone(), serializer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared list of captured key-value pairs, usable in an `OwnedKVList`
+struct SharedPairsKV(Arc<[(Key, OwnedValue)]>);
+
+impl KV for SharedPairsKV {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> Result {
+        PairsKV(&self.0).serialize(record, serializer)
+    }
+}
+
+/// Owned, `'static` copy of a `Record` and the `OwnedKVList` it was logged
+/// with
+///
+/// `Record` borrows most of its data, so it can't outlive a call to
+/// `Drain::log`. `OwnedRecord` renders the message and captures every
+/// key-value pair (of both the record and the logger) into `OwnedValue`s,
+/// retaining their types, so it can be stored, sent to another thread, and
+/// later replayed into any `Drain` with `OwnedRecord::replay`.
+///
+/// Lazy values (eg. `FnValue`) are evaluated once, when the `OwnedRecord`
+/// is created.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Logger, OwnedKVList, OwnedRecord, Record};
+/// use std::sync::Mutex;
+///
+/// /// Drain keeping every record for later
+/// struct Keep(Mutex<Vec<OwnedRecord>>);
+///
+/// impl Drain for Keep {
+///     type Ok = ();
+///     type Err = slog::Never;
+///     fn log(
+///         &self,
+///         record: &Record<'_>,
+///         values: &OwnedKVList,
+///     ) -> Result<(), slog::Never> {
+///         self.0.lock().unwrap().push(OwnedRecord::new(record, values));
+///         Ok(())
+///     }
+/// }
+///
+/// let keep = std::sync::Arc::new(Keep(Mutex::new(vec![])));
+/// let log = Logger::root(keep.clone(), o!("version" => 2));
+/// slog::info!(log, "hello"; "count" => 5);
+///
+/// for record in keep.0.lock().unwrap().iter() {
+///     assert_eq!(record.msg(), "hello");
+///     record.replay(&Discard).unwrap();
+/// }
+/// ```
+#[must_use = "does nothing by itself"]
+pub struct OwnedRecord {
+    location: RecordLocation,
+    level: Level,
+    tag: String,
+    msg: String,
+    kv: Vec<(Key, OwnedValue)>,
+    values: Arc<[(Key, OwnedValue)]>,
+}
+
+impl OwnedRecord {
+    /// Capture `record` and the key-value pairs of `values`
+    ///
+    /// Values that fail to serialize are skipped.
+    pub fn new(record: &Record<'_>, values: &OwnedKVList) -> Self {
+        let mut kv = Vec::new();
+        let _ = record
+            .kv()
+            .serialize(record, &mut CaptureSerializer::new(&mut kv));
+        let mut logger_values = Vec::new();
+        let _ = values
+            .serialize(record, &mut CaptureSerializer::new(&mut logger_values));
+        OwnedRecord {
+            location: *record.location(),
+            level: record.level(),
+            tag: record.tag().into(),
+            msg: record.msg().to_string(),
+            kv,
+            values: logger_values.into(),
+        }
+    }
+
+    /// Get the rendered message
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    /// Get record logging level
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Get tag
+    ///
+    /// See `Record::tag`
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Get code location
+    pub fn location(&self) -> &RecordLocation {
+        &self.location
+    }
+
+    /// Get line number
+    pub fn line(&self) -> u32 {
+        self.location.line
+    }
+
+    /// Get error column
+    pub fn column(&self) -> u32 {
+        self.location.column
+    }
+
+    /// Get file path
+    pub fn file(&self) -> &'static str {
+        self.location.file
+    }
+
+    /// Get module
+    pub fn module(&self) -> &'static str {
+        self.location.module
+    }
+
+    /// Get function (placeholder)
+    ///
+    /// See `Record::function`
+    pub fn function(&self) -> &'static str {
+        self.location.function
+    }
+
+    /// Get the key-value pairs of the record, in the order they were emitted
+    pub fn kv(&self) -> &[(Key, OwnedValue)] {
+        &self.kv
+    }
+
+    /// Get the key-value pairs of the logger, in the order they were emitted
+    ///
+    /// Like `OwnedKVList`, this goes from the most specific `Logger` to
+    /// the root one.
+    pub fn values(&self) -> &[(Key, OwnedValue)] {
+        &self.values
+    }
+
+    /// Look up the value of `key`
+    ///
+    /// The record's own key-value pairs take precedence over the ones of the
+    /// logger.
+    pub fn get(&self, key: &str) -> Option<&OwnedValue> {
+        self.kv
+            .iter()
+            .chain(self.values.iter())
+            .find(|(k, _)| AsRef::<str>::as_ref(k) == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Recreate a `Record` and `OwnedKVList` and pass them to `f`
+    pub fn with_record<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&Record<'_>, &OwnedKVList) -> R,
+    {
+        let rstatic = RecordStatic {
+            location: &self.location,
+            tag: &self.tag,
+            level: self.level,
+        };
+        let values =
+            OwnedKVList::from(OwnedKV(SharedPairsKV(self.values.clone())));
+        f(
+            &Record::new(
+                &rstatic,
+                &format_args!("{}", self.msg),
+                BorrowedKV(&PairsKV(&self.kv)),
+            ),
+            &values,
+        )
+    }
+
+    /// Log this record into `drain`
+    pub fn replay<D>(&self, drain: &D) -> result::Result<D::Ok, D::Err>
+    where
+        D: Drain + ?Sized,
+    {
+        self.with_record(|record, values| drain.log(record, values))
+    }
+}
+
+impl Clone for OwnedRecord {
+    fn clone(&self) -> Self {
+        OwnedRecord {
+            location: self.location,
+            level: self.level,
+            tag: self.tag.clone(),
+            msg: self.msg.clone(),
+            kv: self.kv.clone(),
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for OwnedRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedRecord")
+            .field("level", &self.level)
+            .field("tag", &self.tag)
+            .field("module", &self.location.module)
+            .field("file", &self.location.file)
+            .field("line", &self.location.line)
+            .field("msg", &self.msg)
+            .field("kv", &self.kv)
+            .field("values", &self.values)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Keep(Arc<Mutex<Vec<OwnedRecord>>>);
+
+    impl Drain for Keep {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            values: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            self.0
+                .lock()
+                .unwrap()
+                .push(OwnedRecord::new(record, values));
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestError(&'static str, Option<Box<TestError>>);
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    impl StdError for TestError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            self.1.as_ref().map(|e| &**e as _)
+        }
+    }
+
+    #[test]
+    fn captures_record_and_logger_values() {
+        let keep = Keep::default();
+        let root = Logger::root(keep.clone(), o!("root" => "r"));
+        let log = root.new(o!("child" => 1u8));
+        crate::warn!(log, #"my-tag", "hello {}", "world"; "n" => 5u64, "flag" => true);
+
+        let records = keep.0.lock().unwrap();
+        let record = &records[0];
+        assert_eq!(record.level(), Level::Warning);
+        assert_eq!(record.tag(), "my-tag");
+        assert_eq!(record.msg(), "hello world");
+        assert_eq!(record.module(), module_path!());
+        assert_eq!(record.get("n"), Some(&OwnedValue::U64(5)));
+        assert_eq!(record.get("flag"), Some(&OwnedValue::Bool(true)));
+        assert_eq!(record.get("child"), Some(&OwnedValue::U8(1)));
+        assert_eq!(record.get("root"), Some(&OwnedValue::Str("r".into())));
+        let keys: Vec<&str> =
+            record.values().iter().map(|(k, _)| k.as_ref()).collect();
+        assert_eq!(keys, ["child", "root"]);
+    }
+
+    #[test]
+    fn replay_preserves_record() {
+        let keep = Keep::default();
+        let log = Logger::root(keep.clone(), o!("a" => "b"));
+        let err = TestError("outer", Some(Box::new(TestError("inner", None))));
+        crate::error!(log, "failed"; "err" => #&err, "bytes" => &b"\x01\x02"[..]);
+
+        let original = keep.0.lock().unwrap().remove(0);
+        let again = Keep::default();
+        original.replay(&again).unwrap();
+        let replayed = again.0.lock().unwrap().remove(0);
+
+        assert_eq!(replayed.level(), original.level());
+        assert_eq!(replayed.line(), original.line());
+        assert_eq!(replayed.msg(), original.msg());
+        assert_eq!(replayed.kv(), original.kv());
+        assert_eq!(replayed.values(), original.values());
+        assert_eq!(replayed.get("err").unwrap().to_string(), "outer: inner");
+    }
+
+    #[cfg(feature = "nested-values")]
+    #[test]
+    fn captures_serde_values() {
+        let keep = Keep::default();
+        let log = Logger::root(keep.clone(), o!());
+        crate::info!(log, "nested"; "list" => crate::Serde(vec![1, 2, 3]));
+
+        let record = keep.0.lock().unwrap().remove(0);
+        match record.get("list") {
+            Some(OwnedValue::Serde(_)) => {}
+            other => panic!("expected a nested value, got {:?}", other),
+        }
+        let replayed = Keep::default();
+        record.clone().replay(&replayed).unwrap();
+        assert!(matches!(
+            replayed.0.lock().unwrap()[0].get("list"),
+            Some(OwnedValue::Serde(_))
+        ));
+    }
+}
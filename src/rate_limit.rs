@@ -0,0 +1,221 @@
+//! Rate limiting records per callsite
+//!
+//! See [`RateLimit`].
+
+use core::result;
+use core::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+
+use crate::{
+    Drain, FlushError, Level, OwnedKVList, Record, RecordLocation, RecordStatic,
+};
+
+/// Identifies a callsite
+///
+/// This is not the address of the `RecordLocation`: records re-created at
+/// runtime (e.g. replayed from an `OwnedRecord`) have the same location
+/// at a different address.
+pub(crate) type Callsite = (&'static str, u32, u32);
+
+pub(crate) fn callsite(location: &RecordLocation) -> Callsite {
+    (location.file, location.line, location.column)
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    suppressed: u64,
+}
+
+/// `Drain` limiting how often each callsite can log
+///
+/// Every callsite (logging statement) gets its own token bucket, holding up
+/// to `burst` tokens and refilled at `burst` tokens per `period`. Logging a
+/// record takes a token; records logged while the bucket is empty are
+/// suppressed.
+///
+/// When a callsite logs again after some of its records were suppressed, a
+/// summary record is logged first, with the same location, level and tag and
+/// a `suppressed` key holding the number of suppressed records.
+///
+/// ```
+/// use slog::{o, Discard, Drain, RateLimit};
+/// use std::time::Duration;
+///
+/// // At most 10 records per second from each logging statement
+/// let drain = RateLimit::new(Discard, 10, Duration::from_secs(1)).fuse();
+/// let log = slog::Logger::root(drain, o!());
+/// for i in 0..1000 {
+///     slog::warn!(log, "hot loop"; "i" => i);
+/// }
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct RateLimit<D: Drain> {
+    drain: D,
+    burst: f64,
+    /// Tokens per second
+    rate: f64,
+    buckets: Mutex<HashMap<Callsite, Bucket>>,
+}
+
+impl<D: Drain> RateLimit<D> {
+    /// Create `RateLimit` allowing `burst` records per `period` from each
+    /// callsite
+    ///
+    /// # Panics
+    ///
+    /// If `burst` is zero or `period` is zero.
+    pub fn new(drain: D, burst: u32, period: Duration) -> Self {
+        assert!(burst > 0, "RateLimit burst must be positive");
+        assert!(period > Duration::from_secs(0), "RateLimit period is zero");
+        let burst = f64::from(burst);
+        RateLimit {
+            drain,
+            burst,
+            rate: burst / period.as_secs_f64(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `location`
+    ///
+    /// Returns `None` if the record must be suppressed, or the number of
+    /// records suppressed since the last one that was let through.
+    fn acquire(&self, location: &RecordLocation) -> Option<u64> {
+        let now = Instant::now();
+        let mut buckets =
+            self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let bucket = buckets.entry(callsite(location)).or_insert(Bucket {
+            tokens: self.burst,
+            last_refill: now,
+            suppressed: 0,
+        });
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Some(core::mem::replace(&mut bucket.suppressed, 0))
+        } else {
+            bucket.suppressed += 1;
+            None
+        }
+    }
+}
+
+impl<D: Drain> Drain for RateLimit<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        let suppressed = match self.acquire(record.location()) {
+            Some(suppressed) => suppressed,
+            None => return Ok(None),
+        };
+        if suppressed > 0 {
+            let rs = RecordStatic {
+                location: record.location(),
+                level: record.level(),
+                tag: record.tag(),
+            };
+            self.drain.log(
+                &Record::new(
+                    &rs,
+                    &format_args!(
+                        "suppressed {} records by rate limit",
+                        suppressed
+                    ),
+                    crate::b!("suppressed" => suppressed),
+                ),
+                logger_values,
+            )?;
+        }
+        Ok(Some(self.drain.log(record, logger_values)?))
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never, Serializer, KV};
+    use alloc::string::{String, ToString};
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            struct Suppressed(String);
+            impl Serializer for Suppressed {
+                fn emit_arguments(
+                    &mut self,
+                    key: crate::Key,
+                    val: &fmt::Arguments<'_>,
+                ) -> crate::Result {
+                    if key == "suppressed" {
+                        self.0 = val.to_string();
+                    }
+                    Ok(())
+                }
+            }
+            let mut suppressed = Suppressed(String::new());
+            record.kv().serialize(record, &mut suppressed).unwrap();
+            let mut message = record.msg().to_string();
+            if !suppressed.0.is_empty() {
+                message = alloc::format!("{} ({})", message, suppressed.0);
+            }
+            self.0.lock().unwrap().push(message);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn limits_per_callsite() {
+        fn a(log: &Logger) {
+            crate::info!(log, "a");
+        }
+        fn b(log: &Logger) {
+            crate::info!(log, "b");
+        }
+
+        let collect = Collect::default();
+        let period = Duration::from_millis(100);
+        let log = Logger::root(
+            RateLimit::new(collect.clone(), 2, period).fuse(),
+            o!(),
+        );
+        for _ in 0..5 {
+            a(&log);
+        }
+        b(&log);
+        std::thread::sleep(period);
+        a(&log);
+
+        assert_eq!(
+            *collect.0.lock().unwrap(),
+            ["a", "a", "b", "suppressed 3 records by rate limit (3)", "a"]
+        );
+    }
+}
@@ -0,0 +1,509 @@
+//! Redacting sensitive values
+//!
+//! See [`Redact`].
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{self, Write as _};
+use core::result;
+use core::str::Chars;
+
+use crate::sample::Fnv1a;
+use crate::{
+    BorrowedKV, BytesAsFmt, BytesKind, Drain, FlushError, Key, Level, OwnedKV,
+    OwnedKVList, Record, RecordStatic, Serializer, KV,
+};
+
+/// What `Redact` does with the value of a matching key
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RedactMode {
+    /// Replace the value with the mask, `[REDACTED]` by default
+    Mask,
+    /// Replace the value with a salted hash of it, as 16 hex digits
+    ///
+    /// Equal values have equal hashes, so they can still be correlated
+    /// across records. The hash is not cryptographic: it hides values from
+    /// casual readers, but values from a small set can be guessed by
+    /// anyone who knows the salt.
+    Hash,
+    /// Remove the key-value pair altogether
+    Drop,
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    names: Vec<(String, RedactMode)>,
+    globs: Vec<(String, RedactMode)>,
+    mask: String,
+    salt: String,
+}
+
+impl Rules {
+    fn mode(&self, key: &str) -> Option<RedactMode> {
+        self.names
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .or_else(|| {
+                self.globs
+                    .iter()
+                    .find(|(pattern, _)| glob_matches(pattern, key))
+            })
+            .map(|(_, mode)| *mode)
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any sequence of
+/// characters and `?` any single character, ignoring ASCII case
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let (mut p, mut t) = (pattern.chars(), text.chars());
+    // The pattern after the last `*`, and the text it is matched against
+    let mut star: Option<(Chars<'_>, Chars<'_>)> = None;
+    loop {
+        let mut p_next = p.clone();
+        let mut t_next = t.clone();
+        match (p_next.next(), t_next.next()) {
+            (Some('*'), _) => {
+                star = Some((p_next.clone(), t.clone()));
+                p = p_next;
+                continue;
+            }
+            (None, None) => return true,
+            (Some(pc), Some(tc))
+                if pc == '?' || pc.eq_ignore_ascii_case(&tc) =>
+            {
+                p = p_next;
+                t = t_next;
+                continue;
+            }
+            _ => {}
+        }
+        // Mismatch: let the last `*` match one more character
+        match star {
+            Some((ref star_p, ref mut star_t)) => {
+                if star_t.next().is_none() {
+                    return false;
+                }
+                p = star_p.clone();
+                t = star_t.clone();
+            }
+            None => return false,
+        }
+    }
+}
+
+/// `Drain` rewriting the values of sensitive keys
+///
+/// Applies to the key-value pairs of both the record and the logger, so
+/// values added by a parent `Logger` are redacted too. Keys are matched by
+/// name, or by glob patterns where `*` matches any sequence of characters
+/// and `?` any single character, ignoring ASCII case. Names take
+/// precedence over patterns, and patterns are tried in the order they were
+/// added.
+///
+/// Nested values (`Serializer::emit_serde`) are redacted as a whole; the
+/// keys inside them are not matched. They can only be hashed if they
+/// implement `SerdeValue::serialize_fallback`, and are masked otherwise.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Redact, RedactMode};
+///
+/// let drain = Redact::new(Discard)
+///     .key("password", RedactMode::Drop)
+///     .key("authorization", RedactMode::Mask)
+///     .glob("*_token", RedactMode::Hash)
+///     .salt("per-deployment secret");
+/// let log = slog::Logger::root(drain, o!("authorization" => "Bearer x"));
+/// slog::info!(log, "logged in"; "session_token" => "abc", "password" => "x");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Redact<D: Drain> {
+    drain: D,
+    rules: Arc<Rules>,
+}
+
+impl<D: Drain> Redact<D> {
+    /// Create `Redact`, without any rules
+    pub fn new(drain: D) -> Self {
+        Redact {
+            drain,
+            rules: Arc::new(Rules {
+                names: Vec::new(),
+                globs: Vec::new(),
+                mask: "[REDACTED]".to_string(),
+                salt: String::new(),
+            }),
+        }
+    }
+
+    fn rules_mut(&mut self) -> &mut Rules {
+        Arc::make_mut(&mut self.rules)
+    }
+
+    /// Redact the values of keys named `name`
+    pub fn key(mut self, name: &str, mode: RedactMode) -> Self {
+        self.rules_mut().names.push((name.to_string(), mode));
+        self
+    }
+
+    /// Redact the values of keys matching the glob `pattern`
+    pub fn glob(mut self, pattern: &str, mode: RedactMode) -> Self {
+        self.rules_mut().globs.push((pattern.to_string(), mode));
+        self
+    }
+
+    /// Set the text replacing values in `RedactMode::Mask`
+    pub fn mask(mut self, mask: &str) -> Self {
+        self.rules_mut().mask = mask.to_string();
+        self
+    }
+
+    /// Set the salt of hashes in `RedactMode::Hash`
+    ///
+    /// Without a salt, anyone can compute the hash of a guessed value.
+    pub fn salt(mut self, salt: &str) -> Self {
+        self.rules_mut().salt = salt.to_string();
+        self
+    }
+}
+
+impl<D: Drain> Drain for Redact<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        let rs = RecordStatic {
+            location: record.location(),
+            level: record.level(),
+            tag: record.tag(),
+        };
+        let kv = RedactKV {
+            kv: record.kv().0,
+            rules: &self.rules,
+        };
+        let values = OwnedKVList::from(OwnedKV(RedactValues {
+            values: logger_values.clone(),
+            rules: self.rules.clone(),
+        }));
+        self.drain
+            .log(&Record::new(&rs, record.msg(), BorrowedKV(&kv)), &values)
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+struct RedactKV<'a> {
+    kv: &'a dyn KV,
+    rules: &'a Rules,
+}
+
+impl KV for RedactKV<'_> {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        self.kv.serialize(
+            record,
+            &mut RedactSerializer {
+                inner: serializer,
+                rules: self.rules,
+            },
+        )
+    }
+}
+
+struct RedactValues {
+    values: OwnedKVList,
+    rules: Arc<Rules>,
+}
+
+impl KV for RedactValues {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        self.values.serialize(
+            record,
+            &mut RedactSerializer {
+                inner: serializer,
+                rules: &self.rules,
+            },
+        )
+    }
+}
+
+struct RedactSerializer<'a> {
+    inner: &'a mut dyn Serializer,
+    rules: &'a Rules,
+}
+
+impl RedactSerializer<'_> {
+    fn hash(&self) -> Fnv1a {
+        let mut hasher = Fnv1a::new();
+        hasher.write(self.rules.salt.as_bytes());
+        // Separates the salt from the value
+        hasher.write(&[0xff]);
+        hasher
+    }
+
+    /// Emit a redacted value, given its formatted form
+    fn redact(
+        &mut self,
+        key: Key,
+        mode: RedactMode,
+        val: &fmt::Arguments<'_>,
+    ) -> crate::Result {
+        match mode {
+            RedactMode::Mask => self.inner.emit_str(key, &self.rules.mask),
+            RedactMode::Hash => {
+                let mut hasher = self.hash();
+                let _ = hasher.write_fmt(*val);
+                self.emit_hash(key, &hasher)
+            }
+            RedactMode::Drop => Ok(()),
+        }
+    }
+
+    fn emit_hash(&mut self, key: Key, hasher: &Fnv1a) -> crate::Result {
+        self.inner
+            .emit_arguments(key, &format_args!("{:016x}", hasher.finish()))
+    }
+}
+
+macro_rules! redact_emit {
+    ($($(#[$attr:meta])* $name:ident($ty:ty)),* $(,)?) => {$(
+        $(#[$attr])*
+        fn $name(&mut self, key: Key, val: $ty) -> crate::Result {
+            match self.rules.mode(key.as_ref()) {
+                None => self.inner.$name(key, val),
+                Some(mode) => self.redact(key, mode, &format_args!("{}", val)),
+            }
+        }
+    )*};
+}
+
+impl Serializer for RedactSerializer<'_> {
+    redact_emit!(
+        emit_usize(usize),
+        emit_isize(isize),
+        emit_bool(bool),
+        emit_char(char),
+        emit_u8(u8),
+        emit_i8(i8),
+        emit_u16(u16),
+        emit_i16(i16),
+        emit_u32(u32),
+        emit_i32(i32),
+        emit_f32(f32),
+        emit_u64(u64),
+        emit_i64(i64),
+        emit_f64(f64),
+        emit_u128(u128),
+        emit_i128(i128),
+        emit_str(&str),
+        emit_arguments(&fmt::Arguments<'_>),
+    );
+
+    fn emit_unit(&mut self, key: Key) -> crate::Result {
+        match self.rules.mode(key.as_ref()) {
+            None => self.inner.emit_unit(key),
+            Some(mode) => self.redact(key, mode, &format_args!("()")),
+        }
+    }
+
+    fn emit_none(&mut self, key: Key) -> crate::Result {
+        match self.rules.mode(key.as_ref()) {
+            None => self.inner.emit_none(key),
+            Some(mode) => self.redact(key, mode, &format_args!("")),
+        }
+    }
+
+    fn emit_bytes(
+        &mut self,
+        key: Key,
+        bytes: &[u8],
+        kind: BytesKind,
+    ) -> crate::Result {
+        match self.rules.mode(key.as_ref()) {
+            None => self.inner.emit_bytes(key, bytes, kind),
+            Some(mode) => self.redact(
+                key,
+                mode,
+                &format_args!("{}", BytesAsFmt { bytes, kind }),
+            ),
+        }
+    }
+
+    #[cfg(has_std_error)]
+    fn emit_error(
+        &mut self,
+        key: Key,
+        error: &(dyn crate::StdError + 'static),
+    ) -> crate::Result {
+        match self.rules.mode(key.as_ref()) {
+            None => self.inner.emit_error(key, error),
+            Some(mode) => self.redact(
+                key,
+                mode,
+                &format_args!("{}", crate::ErrorAsFmt(error)),
+            ),
+        }
+    }
+
+    #[cfg(feature = "nested-values")]
+    fn emit_serde(
+        &mut self,
+        key: Key,
+        value: &dyn crate::SerdeValue,
+    ) -> crate::Result {
+        match self.rules.mode(key.as_ref()) {
+            None => self.inner.emit_serde(key, value),
+            Some(RedactMode::Hash) => {
+                // Hash the rendering serializers fall back to, if any
+                let mut hasher = self.hash();
+                let rendered = value.serialize_fallback(
+                    Key::default(),
+                    &mut crate::AsFmtSerializer(|_, val| {
+                        let _ = hasher.write_fmt(val);
+                        Ok(())
+                    }),
+                );
+                match rendered {
+                    Ok(()) => self.emit_hash(key, &hasher),
+                    Err(_) => self.inner.emit_str(key, &self.rules.mask),
+                }
+            }
+            Some(mode) => self.redact(key, mode, &format_args!("")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use std::sync::Mutex;
+
+    /// Collects formatted key-value pairs
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    struct Line(String);
+
+    impl Serializer for Line {
+        fn emit_arguments(
+            &mut self,
+            key: Key,
+            val: &fmt::Arguments<'_>,
+        ) -> crate::Result {
+            let _ = write!(self.0, " {}={}", key, val);
+            Ok(())
+        }
+
+        #[cfg(feature = "nested-values")]
+        fn emit_serde(
+            &mut self,
+            key: Key,
+            _: &dyn crate::SerdeValue,
+        ) -> crate::Result {
+            let _ = write!(self.0, " {}=<serde>", key);
+            Ok(())
+        }
+    }
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            values: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            let mut line = Line(String::new());
+            record.kv().serialize(record, &mut line).unwrap();
+            values.serialize(record, &mut line).unwrap();
+            self.0.lock().unwrap().push(line.0.trim_start().to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("*_token", "session_token"));
+        assert!(glob_matches("*_TOKEN", "Session_Token"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*c", "axxbyybc"));
+        assert!(glob_matches("pass?ord", "password"));
+        assert!(!glob_matches("*_token", "token"));
+        assert!(!glob_matches("a*b", "ab_"));
+        assert!(!glob_matches("pass?ord", "passord"));
+    }
+
+    #[test]
+    fn redacts_record_and_logger_values() {
+        let collect = Collect::default();
+        let drain = Redact::new(collect.clone())
+            .key("password", RedactMode::Drop)
+            .key("Authorization", RedactMode::Mask)
+            .glob("*_token", RedactMode::Hash)
+            .salt("salt");
+        let log = Logger::root(drain, o!("authorization" => "Bearer x"));
+        let log = log.new(o!("password" => "hunter2"));
+        crate::info!(log, "a"; "api_token" => "abc", "password" => 5);
+        crate::info!(log, "b"; "user" => "bob", "user_token" => %"abc");
+
+        let lines = collect.0.lock().unwrap();
+        let (hash, rest) = lines[0].split_at("api_token=".len() + 16);
+        assert_eq!(rest, " authorization=[REDACTED]");
+        // Same value, same hash, whichever way it was emitted
+        assert_eq!(
+            lines[1],
+            alloc::format!(
+                "{} user=bob authorization=[REDACTED]",
+                hash.replace("api", "user")
+            )
+        );
+
+        let unsalted = Collect::default();
+        let log = Logger::root(
+            Redact::new(unsalted.clone()).glob("*_token", RedactMode::Hash),
+            o!(),
+        );
+        crate::info!(log, "a"; "api_token" => "abc");
+        assert_ne!(unsalted.0.lock().unwrap()[0], hash);
+    }
+
+    #[cfg(feature = "nested-values")]
+    #[test]
+    fn redacts_serde_values() {
+        let collect = Collect::default();
+        let drain = Redact::new(collect.clone())
+            .key("secrets", RedactMode::Mask)
+            .key("hashed", RedactMode::Hash)
+            .key("dropped", RedactMode::Drop);
+        let log = Logger::root(drain, o!());
+        crate::info!(log, "a";
+            "secrets" => crate::Serde(alloc::vec!["x"]),
+            "hashed" => crate::Serde(alloc::vec!["x"]),
+            "dropped" => crate::Serde(alloc::vec!["x"]),
+            "public" => crate::Serde(alloc::vec![1]),
+        );
+        // `Serde` doesn't implement `serialize_fallback`, so can't be hashed
+        assert_eq!(
+            collect.0.lock().unwrap()[0],
+            "public=<serde> hashed=[REDACTED] secrets=[REDACTED]"
+        );
+    }
+}
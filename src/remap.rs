@@ -0,0 +1,155 @@
+//! Changing the level of records
+//!
+//! See [`Remap`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::result;
+
+use crate::directive::is_module_prefix;
+use crate::{Drain, FlushError, Level, OwnedKVList, Record};
+
+enum Matcher {
+    Module(String),
+    Tag(String),
+    MsgContains(String),
+}
+
+/// `Drain` changing the level of records before passing them on
+///
+/// Rules are checked in the order they were added, and the first one
+/// matching a record sets its level. Records matching no rule keep their
+/// level.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Level, Remap};
+///
+/// let drain = Remap::new(Discard)
+///     .module("hyper", Level::Debug)
+///     .tag("healthcheck", Level::Trace)
+///     .msg_contains("connection reset", Level::Info);
+/// let log = slog::Logger::root(drain, o!());
+/// slog::error!(log, "connection reset by peer"); // logged at `Info`
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Remap<D: Drain> {
+    drain: D,
+    rules: Vec<(Matcher, Level)>,
+}
+
+impl<D: Drain> Remap<D> {
+    /// Create `Remap`, without any rules
+    pub fn new(drain: D) -> Self {
+        Remap {
+            drain,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Log records of `module` and its submodules at `level`
+    pub fn module(mut self, module: &str, level: Level) -> Self {
+        self.rules
+            .push((Matcher::Module(module.to_string()), level));
+        self
+    }
+
+    /// Log records tagged exactly `tag` at `level`
+    pub fn tag(mut self, tag: &str, level: Level) -> Self {
+        self.rules.push((Matcher::Tag(tag.to_string()), level));
+        self
+    }
+
+    /// Log records with a message containing `text` at `level`
+    pub fn msg_contains(mut self, text: &str, level: Level) -> Self {
+        self.rules
+            .push((Matcher::MsgContains(text.to_string()), level));
+        self
+    }
+
+    fn level_for(&self, record: &Record<'_>) -> Option<Level> {
+        // Only format the message once, and only if a rule needs it
+        let mut msg = None;
+        self.rules
+            .iter()
+            .find(|(matcher, _)| match matcher {
+                Matcher::Module(module) => {
+                    is_module_prefix(module, record.module())
+                }
+                Matcher::Tag(tag) => record.tag() == tag,
+                Matcher::MsgContains(text) => msg
+                    .get_or_insert_with(|| record.msg().to_string())
+                    .contains(text.as_str()),
+            })
+            .map(|&(_, level)| level)
+    }
+}
+
+impl<D: Drain> Drain for Remap<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        match self.level_for(record) {
+            Some(level) if level != record.level() => record
+                .with_level(level, |record| {
+                    self.drain.log(record, logger_values)
+                }),
+            _ => self.drain.log(record, logger_values),
+        }
+    }
+    /// Check if the level, or any level the rules remap to, is enabled
+    ///
+    /// The record is unknown, so any record may be remapped.
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+            || self
+                .rules
+                .iter()
+                .any(|&(_, level)| self.drain.is_enabled(level))
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CaptureDrain;
+    use crate::{o, Logger};
+
+    #[test]
+    fn remaps_by_first_matching_rule() {
+        let capture = CaptureDrain::new();
+        let drain = Remap::new(capture.clone())
+            .tag("noisy", Level::Debug)
+            .msg_contains("reset", Level::Info)
+            .module("slog::remap::tests", Level::Warning)
+            .module("slog::remap::test", Level::Trace);
+        let log = Logger::root(drain, o!());
+        crate::error!(log, #"noisy", "connection reset");
+        crate::error!(log, #"other", "connection reset"; "n" => 1);
+        crate::error!(log, "timeout");
+
+        let records = capture.take();
+        let levels: Vec<_> = records.iter().map(|r| r.level()).collect();
+        assert_eq!(levels, [Level::Debug, Level::Info, Level::Warning]);
+        assert_eq!(records[1].tag(), "other");
+        assert_eq!(records[1].msg(), "connection reset");
+        assert!(records[1].get("n").is_some());
+    }
+
+    #[test]
+    fn keeps_unmatched_records() {
+        let capture = CaptureDrain::new();
+        let drain = Remap::new(capture.clone()).module("hyper", Level::Debug);
+        let log = Logger::root(drain, o!());
+        crate::warn!(log, "kept");
+        assert_eq!(capture.take()[0].level(), Level::Warning);
+    }
+}
@@ -0,0 +1,245 @@
+//! Retrying failed log calls
+//!
+//! See [`Retry`].
+
+use alloc::boxed::Box;
+use core::result;
+use core::time::Duration;
+use std::thread;
+use std::time::Instant;
+
+use crate::{maybe, Drain, FlushError, Level, OwnedKVList, Record};
+
+/// Function that can be used to classify errors in `Retry` drain
+pub trait RetryIfFn<E>:
+    'static
+    + maybe::Sync
+    + maybe::Send
+    + maybe::UnwindSafe
+    + maybe::RefUnwindSafe
+    + Fn(&E) -> bool
+{
+}
+
+impl<T, E> RetryIfFn<E> for T where
+    T: 'static
+        + maybe::Sync
+        + maybe::Send
+        + ?Sized
+        + maybe::UnwindSafe
+        + maybe::RefUnwindSafe
+        + Fn(&E) -> bool
+{
+}
+
+/// `Drain` retrying failed log calls with exponential backoff
+///
+/// When the wrapped drain returns an error, the record is logged again after
+/// a backoff, which doubles after each attempt up to a maximum. It gives up,
+/// returning the last error, when:
+///
+/// * the error is not worth retrying, according to `Retry::retry_if`,
+/// * `max_retries` retries have been made,
+/// * or the next retry would start after `max_elapsed` since the first
+///   attempt.
+///
+/// Backoffs block the logging thread. Consider putting `Retry` behind
+/// `Async` if that's a problem.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Retry};
+/// use std::time::Duration;
+///
+/// let drain = Retry::new(Discard)
+///     .max_retries(3)
+///     .backoff(Duration::from_millis(10), Duration::from_millis(100))
+///     .max_elapsed(Duration::from_millis(250))
+///     .fuse();
+/// let _log = slog::Logger::root(drain, o!());
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Retry<D: Drain> {
+    drain: D,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_elapsed: Duration,
+    retry_if: Option<Box<dyn RetryIfFn<D::Err>>>,
+}
+
+impl<D: Drain> Retry<D> {
+    /// Create `Retry`
+    ///
+    /// By default, every error is retried, up to 5 times within 5 seconds,
+    /// with a backoff starting at 10 milliseconds and doubling up to 1
+    /// second.
+    pub fn new(drain: D) -> Self {
+        Retry {
+            drain,
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            max_elapsed: Duration::from_secs(5),
+            retry_if: None,
+        }
+    }
+
+    /// Set the maximum number of retries after the first attempt
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the backoff before the first retry, and the maximum it can grow
+    /// to
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Set the maximum time from the first attempt to the start of the last
+    /// retry
+    pub fn max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = max_elapsed;
+        self
+    }
+
+    /// Only retry errors for which `retry_if` returns `true`
+    pub fn retry_if<F>(mut self, retry_if: F) -> Self
+    where
+        F: RetryIfFn<D::Err>,
+    {
+        self.retry_if = Some(Box::new(retry_if));
+        self
+    }
+}
+
+impl<D: Drain> Drain for Retry<D> {
+    type Ok = D::Ok;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        let start = Instant::now();
+        let mut backoff = self.initial_backoff;
+        let mut retries = 0;
+        loop {
+            let err = match self.drain.log(record, logger_values) {
+                Ok(ok) => return Ok(ok),
+                Err(err) => err,
+            };
+            let retryable = self.retry_if.as_ref().map_or(true, |f| f(&err));
+            if !retryable
+                || retries >= self.max_retries
+                || start.elapsed() + backoff > self.max_elapsed
+            {
+                return Err(err);
+            }
+            thread::sleep(backoff);
+            retries += 1;
+            backoff = backoff
+                .checked_mul(2)
+                .map_or(self.max_backoff, |b| b.min(self.max_backoff));
+        }
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{b, o, record};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Failure {
+        Transient(usize),
+        Permanent,
+    }
+
+    /// Fails with `Transient` the first `failures` times
+    struct Flaky {
+        failures: usize,
+        attempts: AtomicUsize,
+        permanent: bool,
+    }
+
+    impl Flaky {
+        fn new(failures: usize) -> Self {
+            Flaky {
+                failures,
+                attempts: AtomicUsize::new(0),
+                permanent: false,
+            }
+        }
+    }
+
+    impl Drain for Flaky {
+        type Ok = usize;
+        type Err = Failure;
+        fn log(
+            &self,
+            _: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<usize, Failure> {
+            let attempt = self.attempts.fetch_add(1, Ordering::SeqCst);
+            if self.permanent {
+                Err(Failure::Permanent)
+            } else if attempt < self.failures {
+                Err(Failure::Transient(attempt))
+            } else {
+                Ok(attempt)
+            }
+        }
+    }
+
+    fn log<D: Drain>(drain: &D) -> result::Result<D::Ok, D::Err> {
+        drain.log(
+            &record!(Level::Info, "", &format_args!(""), b!()),
+            &OwnedKVList::from(o!()),
+        )
+    }
+
+    #[test]
+    fn retries_until_success() {
+        let zero = Duration::from_secs(0);
+        let retry = Retry::new(Flaky::new(2)).backoff(zero, zero);
+        assert_eq!(log(&retry), Ok(2));
+
+        let retry = Retry::new(Flaky::new(10))
+            .max_retries(3)
+            .backoff(zero, zero);
+        assert_eq!(log(&retry), Err(Failure::Transient(3)));
+    }
+
+    #[test]
+    fn classifier_stops_retries() {
+        let mut flaky = Flaky::new(0);
+        flaky.permanent = true;
+        let retry = Retry::new(flaky)
+            .backoff(Duration::from_secs(0), Duration::from_secs(0))
+            .retry_if(|e: &Failure| *e != Failure::Permanent);
+        assert_eq!(log(&retry), Err(Failure::Permanent));
+        assert_eq!(retry.drain.attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn gives_up_after_max_elapsed() {
+        let backoff = Duration::from_millis(50);
+        let retry = Retry::new(Flaky::new(10))
+            .backoff(backoff, backoff * 10)
+            .max_elapsed(backoff * 2 + backoff / 2);
+        // Retries after 50ms, but not 100ms later
+        assert_eq!(log(&retry), Err(Failure::Transient(1)));
+    }
+}
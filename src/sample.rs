@@ -0,0 +1,349 @@
+//! Sampling records
+//!
+//! See [`Sample`].
+
+use alloc::string::String;
+use core::hash::{BuildHasher, Hasher};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{fmt, result};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+
+use crate::rate_limit::{callsite, Callsite};
+use crate::{
+    Drain, FlushError, Key, Level, OwnedKVList, Record, Serializer, KV,
+};
+
+/// 64-bit FNV-1a hash
+///
+/// Unlike `std`'s default hasher, it is not randomly seeded, so hashes are
+/// stable across runs and processes.
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub(crate) fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// SplitMix64 finalizer, spreading every input bit over the output
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Map a hash to `[0, 1)`
+fn to_unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Serializer hashing the formatted value of the first pair with a given key
+struct KeyHash<'a> {
+    key: &'a str,
+    hash: Option<u64>,
+}
+
+impl Serializer for KeyHash<'_> {
+    fn emit_arguments(
+        &mut self,
+        key: Key,
+        val: &fmt::Arguments<'_>,
+    ) -> crate::Result {
+        if self.hash.is_none() && key == self.key {
+            let mut hasher = Fnv1a::new();
+            fmt::write(&mut hasher, *val)?;
+            self.hash = Some(hasher.finish());
+        }
+        Ok(())
+    }
+}
+
+/// `Drain` logging only a sample of records
+///
+/// Three kinds of sampling can be configured, and a record is logged only if
+/// it passes all of them:
+///
+/// * `Sample::level_rate` keeps a fraction of the records of a level (all of
+///   them by default),
+/// * `Sample::one_in` keeps only the first of every `n` records from each
+///   callsite,
+/// * `Sample::by_key` makes the level rate decision depend only on the value
+///   of a key, instead of being random. All records with the same value are
+///   kept or dropped together, across levels: a value kept at a rate of 1% is
+///   also kept at any higher rate. The key is looked up in the record's
+///   key-value pairs, then in the logger's. Records without the key are
+///   sampled randomly.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Level, Sample};
+///
+/// // Keep 1% of the requests' debug logs, and everything else
+/// let drain = Sample::new(Discard)
+///     .level_rate(Level::Debug, 0.01)
+///     .level_rate(Level::Trace, 0.0)
+///     .by_key("request_id")
+///     .fuse();
+/// let log = slog::Logger::root(drain, o!());
+/// let request = log.new(o!("request_id" => 42));
+/// slog::debug!(request, "sampled with the rest of request 42");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Sample<D: Drain> {
+    drain: D,
+    /// Indexed by `Level::as_usize() - 1`
+    rates: [f64; 6],
+    one_in: u64,
+    callsites: Mutex<HashMap<Callsite, u64>>,
+    key: Option<String>,
+    seed: u64,
+    counter: AtomicUsize,
+}
+
+impl<D: Drain> Sample<D> {
+    /// Create `Sample` keeping every record
+    pub fn new(drain: D) -> Self {
+        Sample {
+            drain,
+            rates: [1.0; 6],
+            one_in: 1,
+            callsites: Mutex::new(HashMap::new()),
+            key: None,
+            seed: RandomState::new().build_hasher().finish(),
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Keep only a fraction of records with the given level
+    ///
+    /// `rate` is clamped to `0.0..=1.0`, where `0.0` drops all of them and
+    /// `1.0` keeps all of them.
+    pub fn level_rate(mut self, level: Level, rate: f64) -> Self {
+        self.rates[level.as_usize() - 1] = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Keep only the first of every `n` records from each callsite
+    ///
+    /// # Panics
+    ///
+    /// If `n` is zero.
+    pub fn one_in(mut self, n: u64) -> Self {
+        assert!(n > 0, "Sample::one_in needs a positive n");
+        self.one_in = n;
+        self
+    }
+
+    /// Sample consistently by the value of `key`
+    ///
+    /// Values are compared by their formatted text.
+    pub fn by_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    fn key_hash(
+        &self,
+        key: &str,
+        record: &Record<'_>,
+        values: &OwnedKVList,
+    ) -> Option<u64> {
+        let mut serializer = KeyHash { key, hash: None };
+        let _ = record.kv().serialize(record, &mut serializer);
+        if serializer.hash.is_none() {
+            let _ = values.serialize(record, &mut serializer);
+        }
+        serializer.hash
+    }
+
+    fn random(&self) -> u64 {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) as u64;
+        mix(self
+            .seed
+            .wrapping_add(n.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+    }
+
+    fn keep_level(&self, record: &Record<'_>, values: &OwnedKVList) -> bool {
+        let rate = self.rates[record.level().as_usize() - 1];
+        if rate >= 1.0 {
+            return true;
+        }
+        if rate <= 0.0 {
+            return false;
+        }
+        let hash = match self.key {
+            Some(ref key) => self.key_hash(key, record, values).map(mix),
+            None => None,
+        };
+        to_unit(hash.unwrap_or_else(|| self.random())) < rate
+    }
+
+    fn keep_callsite(&self, record: &Record<'_>) -> bool {
+        if self.one_in == 1 {
+            return true;
+        }
+        let mut callsites = self
+            .callsites
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let count = callsites.entry(callsite(record.location())).or_insert(0);
+        let keep = *count % self.one_in == 0;
+        *count += 1;
+        keep
+    }
+}
+
+impl<D: Drain> Drain for Sample<D> {
+    type Ok = Option<D::Ok>;
+    type Err = D::Err;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<Self::Ok, Self::Err> {
+        if self.keep_level(record, logger_values) && self.keep_callsite(record)
+        {
+            Ok(Some(self.drain.log(record, logger_values)?))
+        } else {
+            Ok(None)
+        }
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.rates[level.as_usize() - 1] > 0.0 && self.drain.is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.drain.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger, Never};
+    use alloc::string::ToString;
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+
+    /// Collects the message and the `id` key of every record
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<String>>>);
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            values: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            let mut id = String::new();
+            let mut serializer = crate::AsFmtSerializer(|key, val| {
+                if key == "id" {
+                    id = val.to_string();
+                }
+                Ok(())
+            });
+            values.serialize(record, &mut serializer).unwrap();
+            record.kv().serialize(record, &mut serializer).unwrap();
+            self.0.lock().unwrap().push(alloc::format!(
+                "{}{}",
+                record.msg(),
+                id
+            ));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn level_rates() {
+        let collect = Collect::default();
+        let log = Logger::root(
+            Sample::new(collect.clone())
+                .level_rate(Level::Trace, 0.0)
+                .level_rate(Level::Debug, 0.5)
+                .fuse(),
+            o!(),
+        );
+        assert!(!log.is_trace_enabled());
+        for _ in 0..10_000 {
+            crate::trace!(log, "t");
+            crate::debug!(log, "d");
+            crate::info!(log, "i");
+        }
+        let logged = collect.0.lock().unwrap();
+        let count = |msg| logged.iter().filter(|m| *m == msg).count();
+        assert_eq!(count("t"), 0);
+        assert!((4_000..6_000).contains(&count("d")), "{}", count("d"));
+        assert_eq!(count("i"), 10_000);
+    }
+
+    #[test]
+    fn one_in_n_per_callsite() {
+        let collect = Collect::default();
+        let log =
+            Logger::root(Sample::new(collect.clone()).one_in(3).fuse(), o!());
+        for i in 0..7 {
+            crate::info!(log, "a"; "id" => i);
+            crate::info!(log, "b"; "id" => i);
+        }
+        assert_eq!(
+            *collect.0.lock().unwrap(),
+            ["a0", "b0", "a3", "b3", "a6", "b6"]
+        );
+    }
+
+    #[test]
+    fn consistent_by_key() {
+        let collect = Collect::default();
+        let log = Logger::root(
+            Sample::new(collect.clone())
+                .level_rate(Level::Debug, 0.1)
+                .level_rate(Level::Info, 0.5)
+                .by_key("id")
+                .fuse(),
+            o!(),
+        );
+        for i in 0..1_000 {
+            let request = log.new(o!("id" => i));
+            crate::info!(request, "i");
+            crate::debug!(request, "d");
+            crate::info!(log, "k"; "id" => i);
+        }
+        let logged = collect.0.lock().unwrap();
+        let ids = |msg: &str| -> Vec<String> {
+            logged
+                .iter()
+                .filter(|m| m.starts_with(msg))
+                .map(|m| m[1..].to_string())
+                .collect()
+        };
+        let (info, debug) = (ids("i"), ids("d"));
+        assert!((400..600).contains(&info.len()), "{}", info.len());
+        assert!((50..150).contains(&debug.len()), "{}", debug.len());
+        // Same value, same decision, whether in logger or record values
+        assert_eq!(info, ids("k"));
+        // Values kept at a low rate are kept at higher rates
+        assert!(debug.iter().all(|id| info.contains(id)));
+    }
+}
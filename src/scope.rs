@@ -0,0 +1,215 @@
+//! Thread-local scoped `Logger`
+//!
+//! Instead of passing a `&Logger` to every function, [`scope`] makes a
+//! `Logger` the current one of the thread while a closure runs, and
+//! [`logger`] or the macros of this module log to it. Outside of any scope,
+//! the current `Logger` is the [global](crate::global) one, or, with the
+//! `nothreads` feature, a `Logger` discarding everything.
+//!
+//! Scopes nest: the innermost one wins, and the previous `Logger` is
+//! restored when a scope ends, even if its closure panics.
+//!
+//! ```
+//! use slog::{o, Discard, Logger};
+//!
+//! fn handle_request() {
+//!     slog::scope::info!("handling request"; "path" => "/");
+//! }
+//!
+//! let log = Logger::root(Discard, o!("service" => "api"));
+//! slog::scope::scope(&log.new(o!("request_id" => 5)), || handle_request());
+//! ```
+//!
+//! Scoped `Logger`s do not follow work sent to other threads: use `scope`
+//! again there, with `logger()` captured before.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::Logger;
+
+thread_local! {
+    static SCOPES: RefCell<Vec<Logger>> = RefCell::new(Vec::new());
+}
+
+#[cfg(feature = "nothreads")]
+thread_local! {
+    static DEFAULT: Logger = Logger::root(crate::Discard, crate::o!());
+}
+
+/// Pops the scope when dropped, including when unwinding
+struct ScopeGuard;
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        SCOPES.with(|scopes| {
+            let _ = scopes.borrow_mut().pop();
+        });
+    }
+}
+
+/// Run `f` with `logger` as the current `Logger` of this thread
+///
+/// The previous current `Logger` is restored when `f` returns or panics.
+pub fn scope<F, R>(logger: &Logger, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    SCOPES.with(|scopes| scopes.borrow_mut().push(logger.clone()));
+    let _guard = ScopeGuard;
+    f()
+}
+
+/// Get the current `Logger` of this thread
+///
+/// Outside of any [`scope`], this is the global `Logger`.
+pub fn logger() -> Logger {
+    with_logger(Logger::clone)
+}
+
+/// Call `f` with the current `Logger` of this thread
+///
+/// `f` may start new scopes, and may log to drains that do so.
+pub fn with_logger<F, R>(f: F) -> R
+where
+    F: FnOnce(&Logger) -> R,
+{
+    // Clone rather than keep `SCOPES` borrowed, so `f` can use `scope`
+    match SCOPES.with(|scopes| scopes.borrow().last().cloned()) {
+        Some(logger) => f(&logger),
+        #[cfg(not(feature = "nothreads"))]
+        None => f(&crate::global::logger()),
+        #[cfg(feature = "nothreads")]
+        None => DEFAULT.with(f),
+    }
+}
+
+#[doc(inline)]
+pub use crate::{
+    __scope_crit as crit, __scope_debug as debug, __scope_error as error,
+    __scope_info as info, __scope_log as log, __scope_trace as trace,
+    __scope_warn as warn,
+};
+
+/// Log to the current scoped `Logger`
+///
+/// Takes the same arguments as [`slog::log!`](crate::log), without the
+/// logger: `slog::scope::log!(Level::Info, "msg"; "key" => value)`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_log(
+    ($lvl:expr, #$tag:expr, $($args:tt)+) => {
+        $crate::scope::with_logger(|logger| {
+            $crate::log!(logger, $lvl, $tag, $($args)+)
+        })
+    };
+    ($lvl:expr, $($args:tt)+) => {
+        $crate::scope::with_logger(|logger| {
+            $crate::log!(logger, $lvl, "", $($args)+)
+        })
+    };
+);
+
+/// Log critical level record to the current scoped `Logger`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_crit(
+    ($($args:tt)+) => {
+        $crate::scope::log!($crate::Level::Critical, $($args)+)
+    };
+);
+
+/// Log error level record to the current scoped `Logger`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_error(
+    ($($args:tt)+) => {
+        $crate::scope::log!($crate::Level::Error, $($args)+)
+    };
+);
+
+/// Log warning level record to the current scoped `Logger`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_warn(
+    ($($args:tt)+) => {
+        $crate::scope::log!($crate::Level::Warning, $($args)+)
+    };
+);
+
+/// Log info level record to the current scoped `Logger`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_info(
+    ($($args:tt)+) => {
+        $crate::scope::log!($crate::Level::Info, $($args)+)
+    };
+);
+
+/// Log debug level record to the current scoped `Logger`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_debug(
+    ($($args:tt)+) => {
+        $crate::scope::log!($crate::Level::Debug, $($args)+)
+    };
+);
+
+/// Log trace level record to the current scoped `Logger`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __scope_trace(
+    ($($args:tt)+) => {
+        $crate::scope::log!($crate::Level::Trace, $($args)+)
+    };
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CaptureDrain;
+    use crate::{o, Level};
+    use core::panic::AssertUnwindSafe;
+    use std::panic;
+
+    #[test]
+    fn nests_and_restores() {
+        let capture = CaptureDrain::new();
+        let root = Logger::root(capture.clone(), o!("scope" => "outer"));
+        crate::scope::info!("discarded");
+        scope(&root, || {
+            crate::scope::info!("a");
+            scope(&root.new(o!("scope" => "inner")), || {
+                crate::scope::warn!(#"tagged", "b"; "k" => 1);
+            });
+            crate::scope::log!(Level::Error, "c");
+        });
+        crate::scope::info!("discarded");
+
+        let records = capture.take();
+        let got: Vec<_> = records
+            .iter()
+            .map(|r| {
+                format!("{} {} {}", r.msg(), r.tag(), r.get("scope").unwrap())
+            })
+            .collect();
+        assert_eq!(got, ["a  outer", "b tagged inner", "c  outer"]);
+    }
+
+    #[test]
+    fn restores_after_panic() {
+        let capture = CaptureDrain::new();
+        let outer = Logger::root(capture.clone(), o!("scope" => "outer"));
+        let inner = outer.new(o!("scope" => "inner"));
+        scope(&outer, || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                scope(&inner, || panic!("boom"))
+            }));
+            assert!(result.is_err());
+            crate::scope::info!("after");
+        });
+        let records = capture.take();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].get("scope").unwrap().to_string(), "outer");
+    }
+}
@@ -0,0 +1,353 @@
+//! Replacing drains at runtime
+//!
+//! See [`Switch`].
+
+use alloc::sync::Arc;
+use core::ops::Deref;
+use core::{fmt, mem, result};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError, RwLock};
+
+use crate::{
+    Drain, FlushError, Level, Never, OwnedKVList, Record,
+    SendSyncRefUnwindSafeDrain,
+};
+
+type SharedDrain<O, E> = Arc<dyn SendSyncRefUnwindSafeDrain<Ok = O, Err = E>>;
+
+/// `Drain` forwarding to another drain, which can be replaced at runtime
+///
+/// `Logger::root` erases its drain, so it can't be changed afterwards.
+/// Making a `Switch` the root drain (or any part of the drain hierarchy)
+/// allows replacing what is behind it through a `SwitchCtrl`, while other
+/// threads keep logging.
+///
+/// ```
+/// use slog::{o, Discard, Drain, Switch};
+///
+/// let switch = Switch::new(Discard);
+/// let ctrl = switch.ctrl();
+/// let log = slog::Logger::root(switch, o!());
+/// slog::info!(log, "discarded");
+///
+/// ctrl.set(Discard.filter_level(slog::Level::Warning).ignore_res())
+///     .unwrap();
+/// slog::warn!(log, "logged by the new drain");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct Switch<O = (), E = Never>(SwitchCtrl<O, E>);
+
+impl<O, E> Switch<O, E> {
+    /// Create `Switch` forwarding to `drain`
+    pub fn new<D>(drain: D) -> Self
+    where
+        D: SendSyncRefUnwindSafeDrain<Ok = O, Err = E> + 'static,
+    {
+        Switch::from_arc(Arc::new(drain))
+    }
+
+    /// Create `Switch` forwarding to a shared drain
+    pub fn from_arc(drain: SharedDrain<O, E>) -> Self {
+        Switch(SwitchCtrl(Arc::new(RwLock::new(Slot::new(drain)))))
+    }
+
+    /// Get a handle to replace the drain of this `Switch`
+    pub fn ctrl(&self) -> SwitchCtrl<O, E> {
+        self.0.clone()
+    }
+}
+
+impl<O, E> Drain for Switch<O, E> {
+    type Ok = O;
+    type Err = E;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<O, E> {
+        // Not holding the lock while logging, in case the drain logs
+        // through this `Switch` again
+        self.0.enter().drain.log(record, logger_values)
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.0.get().is_enabled(level)
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        self.0.get().flush()
+    }
+}
+
+impl<O, E> fmt::Debug for Switch<O, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Switch")
+    }
+}
+
+/// Drain of a `Switch`, with the number of records being logged to it
+struct Slot<O, E> {
+    drain: SharedDrain<O, E>,
+    in_flight: Mutex<usize>,
+    /// Notified when `in_flight` drops to zero
+    idle: Condvar,
+}
+
+impl<O, E> Slot<O, E> {
+    fn new(drain: SharedDrain<O, E>) -> Arc<Self> {
+        Arc::new(Slot {
+            drain,
+            in_flight: Mutex::new(0),
+            idle: Condvar::new(),
+        })
+    }
+
+    fn in_flight(&self) -> MutexGuard<'_, usize> {
+        // The lock only guards a counter, updated in one step
+        self.in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Counts a record as being logged to a `Slot` until dropped
+struct InFlight<O, E>(Arc<Slot<O, E>>);
+
+impl<O, E> Deref for InFlight<O, E> {
+    type Target = Slot<O, E>;
+
+    fn deref(&self) -> &Slot<O, E> {
+        &self.0
+    }
+}
+
+impl<O, E> Drop for InFlight<O, E> {
+    fn drop(&mut self) {
+        let mut in_flight = self.0.in_flight();
+        *in_flight -= 1;
+        if *in_flight == 0 {
+            self.0.idle.notify_all();
+        }
+    }
+}
+
+/// Handle replacing the drain of a `Switch`
+///
+/// All clones control the same `Switch`.
+pub struct SwitchCtrl<O = (), E = Never>(Arc<RwLock<Arc<Slot<O, E>>>>);
+
+impl<O, E> SwitchCtrl<O, E> {
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Arc<Slot<O, E>>> {
+        // The lock only guards an `Arc`, which can't be left inconsistent
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get the current drain, counting a record as being logged to it
+    fn enter(&self) -> InFlight<O, E> {
+        let current = self.read();
+        // Counted under the lock, so `swap` sees it once it replaced the
+        // drain
+        *current.in_flight() += 1;
+        InFlight(current.clone())
+    }
+
+    /// Get the current drain
+    pub fn get(&self) -> SharedDrain<O, E> {
+        self.read().drain.clone()
+    }
+
+    /// Replace the drain, and return the old one
+    ///
+    /// Blocks until records being logged to the old drain have completed,
+    /// but does not flush it. This must not be called by the old drain while
+    /// it logs a record, since it would wait for itself.
+    pub fn swap(&self, drain: SharedDrain<O, E>) -> SharedDrain<O, E> {
+        let old = mem::replace(
+            &mut *self.0.write().unwrap_or_else(PoisonError::into_inner),
+            Slot::new(drain),
+        );
+        // Records can't start being logged to `old` anymore, so this ends
+        let mut in_flight = old.in_flight();
+        while *in_flight != 0 {
+            in_flight = old
+                .idle
+                .wait(in_flight)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        drop(in_flight);
+        old.drain.clone()
+    }
+
+    /// Replace the drain, and flush the old one
+    ///
+    /// Records logged after this call go to the new drain. This blocks until
+    /// the records the old drain was logging have completed, then flushes
+    /// it. Errors
+    /// flushing it are returned, except `FlushError::NotSupported`.
+    ///
+    /// Like [`swap`](SwitchCtrl::swap), this must not be called by the old
+    /// drain while it logs a record.
+    pub fn set<D>(&self, drain: D) -> result::Result<(), FlushError>
+    where
+        D: SendSyncRefUnwindSafeDrain<Ok = O, Err = E> + 'static,
+    {
+        let old = self.swap(Arc::new(drain));
+        match old.flush() {
+            Ok(()) | Err(FlushError::NotSupported) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<O, E> Clone for SwitchCtrl<O, E> {
+    fn clone(&self) -> Self {
+        SwitchCtrl(self.0.clone())
+    }
+}
+
+impl<O, E> fmt::Debug for SwitchCtrl<O, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SwitchCtrl")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger};
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Collect {
+        logged: Arc<Mutex<Vec<String>>>,
+        /// Number of records logged at each flush
+        flushes: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl Drain for Collect {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            self.logged.lock().unwrap().push(record.msg().to_string());
+            Ok(())
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            let logged = self.logged.lock().unwrap().len();
+            self.flushes.lock().unwrap().push(logged);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn swaps_and_flushes() {
+        let (first, second) = (Collect::default(), Collect::default());
+        let switch = Switch::new(first.clone());
+        let ctrl = switch.ctrl();
+        let log = Logger::root(switch, o!());
+
+        crate::info!(log, "a");
+        ctrl.clone().set(second.clone()).unwrap();
+        crate::info!(log, "b");
+
+        assert_eq!(*first.logged.lock().unwrap(), ["a"]);
+        assert_eq!(*first.flushes.lock().unwrap(), [1]);
+        assert_eq!(*second.logged.lock().unwrap(), ["b"]);
+        assert!(second.flushes.lock().unwrap().is_empty());
+
+        // Replacing the drain flushes the previous one
+        ctrl.set(crate::Discard).unwrap();
+        crate::info!(log, "c");
+        assert_eq!(*second.logged.lock().unwrap(), ["b"]);
+        assert_eq!(*second.flushes.lock().unwrap(), [1]);
+    }
+
+    #[cfg(not(feature = "nothreads"))]
+    #[test]
+    fn swap_while_logging() {
+        let drains: Vec<Collect> =
+            (0..50).map(|_| Collect::default()).collect();
+        let switch = Switch::new(drains[0].clone());
+        let ctrl = switch.ctrl();
+        let log = Logger::root(switch, o!());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let log = log.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        crate::info!(log, "x");
+                    }
+                })
+            })
+            .collect();
+        for drain in &drains[1..] {
+            ctrl.set(drain.clone()).unwrap();
+        }
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        ctrl.set(crate::Discard).unwrap();
+
+        let mut total = 0;
+        for drain in &drains {
+            let logged = drain.logged.lock().unwrap().len();
+            // Flushed once, after everything was logged to it
+            assert_eq!(*drain.flushes.lock().unwrap(), [logged]);
+            total += logged;
+        }
+        assert_eq!(total, 4000);
+    }
+
+    /// Logs `inner` through `log` while logging `outer`, once `set` started
+    #[cfg(not(feature = "nothreads"))]
+    #[derive(Clone)]
+    struct Reentrant {
+        log: Arc<Mutex<Option<Logger>>>,
+        started: Arc<std::sync::Barrier>,
+    }
+
+    #[cfg(not(feature = "nothreads"))]
+    impl Drain for Reentrant {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            record: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            if record.msg().to_string() == "outer" {
+                self.started.wait();
+                // Let `set` replace this drain, and wait for this record
+                std::thread::sleep(core::time::Duration::from_millis(100));
+                let log = self.log.lock().unwrap().clone().unwrap();
+                crate::info!(log, "inner");
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(not(feature = "nothreads"))]
+    #[test]
+    fn logs_through_itself_while_set_waits() {
+        let collect = Collect::default();
+        let reentrant = Reentrant {
+            log: Arc::default(),
+            started: Arc::new(std::sync::Barrier::new(2)),
+        };
+        let switch = Switch::new(reentrant.clone());
+        let ctrl = switch.ctrl();
+        let log = Logger::root(switch, o!());
+        *reentrant.log.lock().unwrap() = Some(log.clone());
+
+        let thread = std::thread::spawn(move || crate::info!(log, "outer"));
+        reentrant.started.wait();
+        ctrl.set(collect.clone()).unwrap();
+        thread.join().unwrap();
+        assert_eq!(*collect.logged.lock().unwrap(), ["inner"]);
+        reentrant.log.lock().unwrap().take();
+    }
+}
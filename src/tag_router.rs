@@ -0,0 +1,239 @@
+//! Routing records by tag
+//!
+//! See [`TagRouter`].
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::{fmt, result};
+
+use crate::{
+    Drain, FlushError, Level, Never, OwnedKVList, Record,
+    SendSyncRefUnwindSafeDrain,
+};
+
+type SharedDrain<O, E> = Arc<dyn SendSyncRefUnwindSafeDrain<Ok = O, Err = E>>;
+
+/// `Drain` sending records to different drains depending on `Record::tag`
+///
+/// A record is sent to:
+///
+/// 1. the drain routed to its exact tag, if any,
+/// 2. otherwise, the drain routed to the longest prefix of its tag, if any,
+/// 3. otherwise, the default drain.
+///
+/// ```
+/// use slog::{o, Discard, Drain, TagRouter};
+///
+/// let drain = TagRouter::new(Discard)
+///     .route("audit", Discard)
+///     .route_prefix("http.", Discard);
+/// let log = slog::Logger::root(drain, o!());
+/// slog::info!(log, #"audit", "user logged in"; "user" => "alice");
+/// slog::info!(log, #"http.access", "GET /");
+/// slog::info!(log, "anything else");
+/// ```
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+pub struct TagRouter<O = (), E = Never> {
+    exact: BTreeMap<String, SharedDrain<O, E>>,
+    /// Sorted by decreasing length, so the first match is the longest
+    prefixes: Vec<(String, SharedDrain<O, E>)>,
+    default: SharedDrain<O, E>,
+}
+
+impl<O, E> TagRouter<O, E> {
+    /// Create `TagRouter` sending all records to `default`, until routes
+    /// are added
+    pub fn new<D>(default: D) -> Self
+    where
+        D: SendSyncRefUnwindSafeDrain<Ok = O, Err = E> + 'static,
+    {
+        TagRouter {
+            exact: BTreeMap::new(),
+            prefixes: Vec::new(),
+            default: Arc::new(default),
+        }
+    }
+
+    /// Send records tagged exactly `tag` to `drain`
+    ///
+    /// Replaces any previous route for the same tag.
+    pub fn route<D>(mut self, tag: &str, drain: D) -> Self
+    where
+        D: SendSyncRefUnwindSafeDrain<Ok = O, Err = E> + 'static,
+    {
+        self.exact.insert(tag.to_string(), Arc::new(drain));
+        self
+    }
+
+    /// Send records with a tag starting with `prefix` to `drain`
+    ///
+    /// Replaces any previous route for the same prefix.
+    pub fn route_prefix<D>(mut self, prefix: &str, drain: D) -> Self
+    where
+        D: SendSyncRefUnwindSafeDrain<Ok = O, Err = E> + 'static,
+    {
+        self.prefixes.retain(|(p, _)| p != prefix);
+        let index = self
+            .prefixes
+            .iter()
+            .position(|(p, _)| p.len() < prefix.len())
+            .unwrap_or(self.prefixes.len());
+        self.prefixes
+            .insert(index, (prefix.to_string(), Arc::new(drain)));
+        self
+    }
+
+    fn drain_for(
+        &self,
+        tag: &str,
+    ) -> &dyn SendSyncRefUnwindSafeDrain<Ok = O, Err = E> {
+        if let Some(drain) = self.exact.get(tag) {
+            return &**drain;
+        }
+        self.prefixes
+            .iter()
+            .find(|(prefix, _)| tag.starts_with(prefix.as_str()))
+            .map_or(&*self.default, |(_, drain)| &**drain)
+    }
+
+    fn drains(&self) -> impl Iterator<Item = &SharedDrain<O, E>> {
+        core::iter::once(&self.default)
+            .chain(self.exact.values())
+            .chain(self.prefixes.iter().map(|(_, drain)| drain))
+    }
+}
+
+impl<O, E> Drain for TagRouter<O, E> {
+    type Ok = O;
+    type Err = E;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<O, E> {
+        self.drain_for(record.tag()).log(record, logger_values)
+    }
+    /// Check if any of the routes is enabled, since the tag is unknown
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        self.drains().any(|drain| drain.is_enabled(level))
+    }
+    /// Flush all the routes
+    ///
+    /// Every route is flushed even if some fail, and the first error is
+    /// returned. Routes not supporting flushing are ignored, unless none of
+    /// them does.
+    fn flush(&self) -> result::Result<(), FlushError> {
+        let mut supported = false;
+        let mut error = None;
+        for drain in self.drains() {
+            match drain.flush() {
+                Ok(()) => supported = true,
+                Err(FlushError::NotSupported) => {}
+                Err(e) => {
+                    supported = true;
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None if supported => Ok(()),
+            None => Err(FlushError::NotSupported),
+        }
+    }
+}
+
+impl<O, E> fmt::Debug for TagRouter<O, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TagRouter")
+            .field("exact", &self.exact.keys().collect::<Vec<_>>())
+            .field(
+                "prefixes",
+                &self.prefixes.iter().map(|(p, _)| p).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Clone, Default)]
+    struct Count(Arc<AtomicUsize>);
+
+    impl Count {
+        fn get(&self) -> usize {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    impl Drain for Count {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            _: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> result::Result<(), Never> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+        fn flush(&self) -> result::Result<(), FlushError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn routes_by_tag() {
+        let (default, audit, http, admin) = (
+            Count::default(),
+            Count::default(),
+            Count::default(),
+            Count::default(),
+        );
+        let log = Logger::root(
+            TagRouter::new(default.clone())
+                .route_prefix("http.", http.clone())
+                .route("audit", audit.clone())
+                .route_prefix("http.admin", admin.clone()),
+            o!(),
+        );
+        crate::info!(log, "default");
+        crate::info!(log, #"audit", "audit");
+        crate::info!(log, #"audit.extra", "default");
+        crate::info!(log, #"http.access", "http");
+        crate::info!(log, #"http.admin.users", "admin");
+        crate::info!(log, #"http.admin", "admin");
+        assert_eq!(
+            (default.get(), audit.get(), http.get(), admin.get()),
+            (2, 1, 1, 2)
+        );
+    }
+
+    #[test]
+    fn flushes_all_routes() {
+        struct NoFlush;
+        impl Drain for NoFlush {
+            type Ok = ();
+            type Err = Never;
+            fn log(
+                &self,
+                _: &Record<'_>,
+                _: &OwnedKVList,
+            ) -> result::Result<(), Never> {
+                Ok(())
+            }
+        }
+
+        let router = TagRouter::new(NoFlush);
+        assert!(matches!(router.flush(), Err(FlushError::NotSupported)));
+        let router = router.route("a", Count::default());
+        assert!(router.flush().is_ok());
+    }
+}
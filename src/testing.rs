@@ -0,0 +1,406 @@
+//! Helpers for testing logging
+//!
+//! [`CaptureDrain`] keeps every record logged to it as an [`OwnedRecord`],
+//! so tests can check what was logged, either by querying it or with the
+//! [`assert_logged!`](crate::assert_logged) and
+//! [`assert_not_logged!`](crate::assert_not_logged) macros.
+//!
+//! ```
+//! use slog::testing::CaptureDrain;
+//! use slog::{assert_logged, assert_not_logged, o, Level};
+//!
+//! let capture = CaptureDrain::new();
+//! let log = slog::Logger::root(capture.clone(), o!("user" => "alice"));
+//! slog::warn!(log, "disk almost full"; "free_mb" => 5);
+//!
+//! assert_logged!(capture, Level::Warning, "almost full"; "free_mb" => 5);
+//! assert_logged!(capture, Level::Warning, "disk"; "user" => "alice");
+//! assert_not_logged!(capture, Level::Error, "disk");
+//! assert_eq!(capture.with_level(Level::Warning).len(), 1);
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{self, Write as _};
+use core::result;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::{
+    Drain, Level, Never, OwnedKVList, OwnedRecord, OwnedValue, Record, Value,
+};
+
+/// `Drain` capturing records, for use in tests
+///
+/// All clones share the same records, so a clone can be given to the
+/// `Logger` while the original is used to inspect what was logged.
+#[derive(Clone, Default)]
+pub struct CaptureDrain {
+    records: Arc<Mutex<Vec<OwnedRecord>>>,
+}
+
+impl CaptureDrain {
+    /// Create an empty `CaptureDrain`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<OwnedRecord>> {
+        // Poisoned by a panicking test; the records are still fine
+        self.records.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Get a copy of all the captured records, in the order they were logged
+    pub fn records(&self) -> Vec<OwnedRecord> {
+        self.lock().clone()
+    }
+
+    /// Remove and return all the captured records
+    pub fn take(&self) -> Vec<OwnedRecord> {
+        core::mem::take(&mut *self.lock())
+    }
+
+    /// Remove all the captured records
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    /// Number of captured records
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Check if no records were captured
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Get the captured records matching `query`
+    pub fn matching(&self, query: &Query) -> Vec<OwnedRecord> {
+        self.lock()
+            .iter()
+            .filter(|record| query.matches(record))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the captured records logged at exactly `level`
+    pub fn with_level(&self, level: Level) -> Vec<OwnedRecord> {
+        self.matching(&Query::new().level(level))
+    }
+
+    /// Get the captured records with a message containing `msg`
+    pub fn containing(&self, msg: &str) -> Vec<OwnedRecord> {
+        self.matching(&Query::new().msg_contains(msg))
+    }
+
+    /// Get the captured records where `key` equals `value`
+    ///
+    /// See `Query::kv` for how values are compared.
+    pub fn with_kv<V: Value>(&self, key: &str, value: V) -> Vec<OwnedRecord> {
+        self.matching(&Query::new().kv(key, value))
+    }
+
+    /// Panic, listing the captured records, unless one of them matches
+    /// `query`
+    ///
+    /// This is what `assert_logged!` calls.
+    #[track_caller]
+    pub fn assert_logged(&self, query: &Query) {
+        let records = self.lock();
+        if records.iter().any(|record| query.matches(record)) {
+            return;
+        }
+        let mut report =
+            alloc::format!("no record matching {}\ncaptured records:", query);
+        if records.is_empty() {
+            report.push_str(" none");
+        }
+        for (i, record) in records.iter().enumerate() {
+            let _ = write!(report, "\n  [{}] {}", i, DisplayRecord(record));
+            for mismatch in query.mismatches(record) {
+                let _ = write!(report, "\n      - {}", mismatch);
+            }
+        }
+        drop(records);
+        panic!("{}", report);
+    }
+
+    /// Panic, listing the offending records, if any of the captured records
+    /// matches `query`
+    ///
+    /// This is what `assert_not_logged!` calls.
+    #[track_caller]
+    pub fn assert_not_logged(&self, query: &Query) {
+        let matching = self.matching(query);
+        if matching.is_empty() {
+            return;
+        }
+        let mut report =
+            alloc::format!("unexpected records matching {}:", query);
+        for record in &matching {
+            let _ = write!(report, "\n  {}", DisplayRecord(record));
+        }
+        panic!("{}", report);
+    }
+}
+
+impl Drain for CaptureDrain {
+    type Ok = ();
+    type Err = Never;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        logger_values: &OwnedKVList,
+    ) -> result::Result<(), Never> {
+        let record = OwnedRecord::new(record, logger_values);
+        self.lock().push(record);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for CaptureDrain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.lock().iter()).finish()
+    }
+}
+
+/// Conditions on captured records
+///
+/// A record matches if it satisfies all the conditions; an empty `Query`
+/// matches any record.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    level: Option<Level>,
+    msg: Option<String>,
+    kv: Vec<(String, OwnedValue)>,
+}
+
+impl Query {
+    /// Create a `Query` matching any record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match records logged at exactly `level`
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Only match records with a message containing `msg`
+    pub fn msg_contains(mut self, msg: &str) -> Self {
+        self.msg = Some(msg.to_string());
+        self
+    }
+
+    /// Only match records where `key`, in the record or the logger
+    /// key-value pairs, equals `value`
+    ///
+    /// Integers compare equal regardless of their type, and so do floats,
+    /// strings and `fmt::Arguments`. Other values must have been emitted
+    /// the same way.
+    pub fn kv<V: Value>(mut self, key: &str, value: V) -> Self {
+        let value = OwnedValue::from_value(
+            &value,
+            &crate::record!(Level::Info, "", &format_args!(""), crate::b!()),
+        )
+        .unwrap_or(OwnedValue::None);
+        self.kv.push((key.to_string(), value));
+        self
+    }
+
+    /// Check if `record` satisfies all the conditions
+    pub fn matches(&self, record: &OwnedRecord) -> bool {
+        self.mismatches(record).is_empty()
+    }
+
+    /// Describe the conditions `record` does not satisfy
+    fn mismatches(&self, record: &OwnedRecord) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if let Some(level) = self.level {
+            if record.level() != level {
+                mismatches.push(alloc::format!(
+                    "level: expected {}, got {}",
+                    level.as_short_str(),
+                    record.level().as_short_str()
+                ));
+            }
+        }
+        if let Some(ref msg) = self.msg {
+            if !record.msg().contains(msg.as_str()) {
+                mismatches
+                    .push(alloc::format!("msg: does not contain {:?}", msg));
+            }
+        }
+        for (key, expected) in &self.kv {
+            match record.get(key) {
+                Some(actual) if loosely_eq(expected, actual) => {}
+                Some(actual) => mismatches.push(alloc::format!(
+                    "{}: expected {:?}, got {:?}",
+                    key,
+                    expected,
+                    actual
+                )),
+                None => mismatches.push(alloc::format!("{}: missing", key)),
+            }
+        }
+        mismatches
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut conditions = Vec::new();
+        if let Some(level) = self.level {
+            conditions.push(alloc::format!("level={}", level.as_short_str()));
+        }
+        if let Some(ref msg) = self.msg {
+            conditions.push(alloc::format!("msg~{:?}", msg));
+        }
+        for (key, value) in &self.kv {
+            conditions.push(alloc::format!("{}={:?}", key, value));
+        }
+        if conditions.is_empty() {
+            f.write_str("any record")
+        } else {
+            f.write_str(&conditions.join(", "))
+        }
+    }
+}
+
+/// Compare integers of any type by value, and floats of any type by value,
+/// but never an integer to a float
+fn loosely_eq(a: &OwnedValue, b: &OwnedValue) -> bool {
+    fn is_float(v: &OwnedValue) -> bool {
+        matches!(*v, OwnedValue::F32(_) | OwnedValue::F64(_))
+    }
+    fn string(v: &OwnedValue) -> Option<&str> {
+        match *v {
+            OwnedValue::Str(ref v) | OwnedValue::Arguments(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    if let (Some(a), Some(b)) = (a.as_i128(), b.as_i128()) {
+        a == b
+    } else if is_float(a) && is_float(b) {
+        a.as_f64() == b.as_f64()
+    } else if let (Some(a), Some(b)) = (string(a), string(b)) {
+        a == b
+    } else {
+        a == b
+    }
+}
+
+/// Formats a record on one line, for failed assertions
+struct DisplayRecord<'a>(&'a OwnedRecord);
+
+impl fmt::Display for DisplayRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let record = self.0;
+        write!(f, "{} {:?}", record.level().as_short_str(), record.msg())?;
+        for (key, value) in record.kv().iter().chain(record.values()) {
+            write!(f, " {}={:?}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Assert that a `CaptureDrain` captured a matching record
+///
+/// Takes the `CaptureDrain`, the level, a substring of the message, and
+/// optionally key-value pairs that must be equal, as described in
+/// [`Query::kv`](crate::testing::Query::kv). On failure, panics listing the
+/// captured records and how each of them differs.
+///
+/// ```
+/// use slog::testing::CaptureDrain;
+/// use slog::{assert_logged, o, Level};
+///
+/// let capture = CaptureDrain::new();
+/// let log = slog::Logger::root(capture.clone(), o!());
+/// slog::warn!(log, "retrying request"; "attempt" => 2);
+/// assert_logged!(capture, Level::Warning, "retrying");
+/// assert_logged!(capture, Level::Warning, "retrying"; "attempt" => 2);
+/// ```
+///
+/// Requires the `std` feature.
+#[macro_export]
+macro_rules! assert_logged {
+    ($capture:expr, $level:expr, $msg:expr $(; $($k:expr => $v:expr),* $(,)?)?) => {
+        $capture.assert_logged(
+            &$crate::testing::Query::new()
+                .level($level)
+                .msg_contains($msg)
+                $($(.kv($k, $v))*)?
+        )
+    };
+}
+
+/// Assert that a `CaptureDrain` captured no matching record
+///
+/// Takes the same arguments as [`assert_logged!`](crate::assert_logged). On
+/// failure, panics listing the matching records.
+///
+/// Requires the `std` feature.
+#[macro_export]
+macro_rules! assert_not_logged {
+    ($capture:expr, $level:expr, $msg:expr $(; $($k:expr => $v:expr),* $(,)?)?) => {
+        $capture.assert_not_logged(
+            &$crate::testing::Query::new()
+                .level($level)
+                .msg_contains($msg)
+                $($(.kv($k, $v))*)?
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{o, Logger};
+
+    #[test]
+    fn queries() {
+        let capture = CaptureDrain::new();
+        let log = Logger::root(capture.clone(), o!("id" => 7u64));
+        crate::info!(log, "started"; "port" => 8080u16);
+        crate::warn!(log, "slow request"; "ms" => 950, "path" => %"/a");
+        crate::warn!(log, "slow request"; "ms" => 20, "path" => "/b");
+
+        assert_eq!(capture.len(), 3);
+        assert_eq!(capture.with_level(Level::Warning).len(), 2);
+        assert_eq!(capture.containing("start").len(), 1);
+        assert_eq!(capture.with_kv("id", 7).len(), 3);
+        assert_eq!(capture.with_kv("path", "/a").len(), 1);
+        assert_eq!(capture.with_kv("port", 8080usize).len(), 1);
+        assert_eq!(capture.with_kv("port", "8080").len(), 0);
+        // Integers and floats do not compare equal
+        assert_eq!(capture.with_kv("port", 8080.0).len(), 0);
+
+        crate::assert_logged!(capture, Level::Warning, "slow"; "ms" => 20,);
+        crate::assert_not_logged!(capture, Level::Info, "slow");
+        assert_eq!(capture.take().len(), 3);
+        assert!(capture.is_empty());
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let capture = CaptureDrain::new();
+        let log = Logger::root(capture.clone(), o!());
+        crate::warn!(log, "slow request"; "ms" => 950);
+
+        let report = std::panic::catch_unwind(|| {
+            crate::assert_logged!(capture, Level::Warning, "slow"; "ms" => 20);
+        })
+        .unwrap_err();
+        assert_eq!(
+            report.downcast_ref::<String>().unwrap(),
+            "no record matching level=WARN, msg~\"slow\", ms=I32(20)\n\
+             captured records:\n  \
+             [0] WARN \"slow request\" ms=I32(950)\n      \
+             - ms: expected I32(20), got I32(950)"
+        );
+    }
+}
@@ -550,3 +550,47 @@ fn can_hash_keys() {
     )]
     let _tab: HashSet<Key> = ["foo"].iter().map(|&k| k.into()).collect();
 }
+
+#[test]
+fn reloadable_level_filter() {
+    use crate::*;
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Count(Arc<AtomicUsize>);
+
+    impl Drain for Count {
+        type Ok = ();
+        type Err = Never;
+        fn log(
+            &self,
+            _: &Record<'_>,
+            _: &OwnedKVList,
+        ) -> core::result::Result<(), Never> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let drain =
+        ReloadableLevelFilter::new(Count(count.clone()), FilterLevel::Info);
+    let handle = drain.handle();
+    let log = Logger::root(drain.fuse(), o!());
+
+    debug!(log, "filtered");
+    info!(log, "logged");
+    assert_eq!(count.load(Ordering::Relaxed), 1);
+    assert!(!log.is_debug_enabled());
+
+    handle.clone().set_level(FilterLevel::Trace);
+    assert_eq!(handle.level(), FilterLevel::Trace);
+    assert!(log.is_debug_enabled());
+    debug!(log, "logged");
+    assert_eq!(count.load(Ordering::Relaxed), 2);
+
+    handle.set_level(FilterLevel::Off);
+    assert!(!log.is_critical_enabled());
+    crit!(log, "filtered");
+    assert_eq!(count.load(Ordering::Relaxed), 2);
+}
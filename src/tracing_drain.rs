@@ -0,0 +1,491 @@
+//! Sending slog records as `tracing` events
+//!
+//! See [`TracingDrain`].
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::result;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use tracing_core::callsite::{self, Callsite};
+use tracing_core::field::{self, Field, FieldSet, Value};
+use tracing_core::metadata::Kind;
+use tracing_core::subscriber::Interest;
+use tracing_core::{dispatcher, Event, LevelFilter, Metadata};
+
+use crate::owned::CaptureSerializer;
+use crate::{
+    Drain, FlushError, Key, Level, Never, OwnedKVList, OwnedValue, Record, KV,
+};
+
+/// Most fields of an event, including the message
+///
+/// `tracing` only builds sets of values from fixed-size arrays.
+const MAX_FIELDS: usize = 32;
+
+fn to_tracing_level(level: Level) -> tracing_core::Level {
+    match level {
+        Level::Critical | Level::Error => tracing_core::Level::ERROR,
+        Level::Warning => tracing_core::Level::WARN,
+        Level::Info => tracing_core::Level::INFO,
+        Level::Debug => tracing_core::Level::DEBUG,
+        Level::Trace => tracing_core::Level::TRACE,
+    }
+}
+
+/// Callsite created at runtime
+///
+/// The `Metadata` of a callsite refers to the callsite, so it is set right
+/// after both are leaked, before registering the callsite.
+struct RuntimeCallsite {
+    metadata: RwLock<Option<&'static Metadata<'static>>>,
+}
+
+impl Callsite for RuntimeCallsite {
+    fn set_interest(&self, _interest: Interest) {}
+
+    fn metadata(&self) -> &Metadata<'_> {
+        self.metadata
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .expect("metadata is set before registering")
+    }
+}
+
+/// Leak a new callsite, with `fields` as field names
+///
+/// The callsite must then be registered.
+fn leak_callsite(
+    name: &'static str,
+    level: tracing_core::Level,
+    location: &crate::RecordLocation,
+    fields: &[&str],
+    kind: Kind,
+) -> (&'static RuntimeCallsite, &'static Metadata<'static>) {
+    let callsite: &'static RuntimeCallsite =
+        Box::leak(Box::new(RuntimeCallsite {
+            metadata: RwLock::new(None),
+        }));
+    let fields: Vec<&'static str> = fields
+        .iter()
+        .map(|name| &*Box::leak(name.to_string().into_boxed_str()))
+        .collect();
+    let metadata: &'static Metadata<'static> =
+        Box::leak(Box::new(Metadata::new(
+            name,
+            location.module,
+            level,
+            Some(location.file),
+            Some(location.line),
+            Some(location.module),
+            FieldSet::new(
+                Box::leak(fields.into_boxed_slice()),
+                callsite::Identifier(callsite),
+            ),
+            kind,
+        )));
+    *callsite
+        .metadata
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(metadata);
+    (callsite, metadata)
+}
+
+/// Leak and register a new callsite, with `fields` as field names
+#[cfg(all(test, not(feature = "nothreads")))]
+pub(crate) fn new_callsite(
+    name: &'static str,
+    level: tracing_core::Level,
+    location: &crate::RecordLocation,
+    fields: &[&str],
+    kind: Kind,
+) -> &'static Metadata<'static> {
+    let (callsite, metadata) =
+        leak_callsite(name, level, location, fields, kind);
+    callsite::register(callsite);
+    metadata
+}
+
+/// Most callsites created for records, process-wide
+///
+/// Records needing a new callsite once this many exist are sent with the
+/// overflow callsite of their level instead.
+const MAX_CALLSITES: usize = 1024;
+
+/// Identifies the callsite of a record
+#[derive(PartialEq, Eq, Hash)]
+struct CallsiteKey {
+    level: tracing_core::Level,
+    module: &'static str,
+    file: &'static str,
+    line: u32,
+    keys: Vec<String>,
+}
+
+#[derive(Default)]
+struct Callsites {
+    by_key: HashMap<CallsiteKey, &'static Metadata<'static>>,
+    /// Callsites with only a `message` and a `kv` field, by level
+    overflow: HashMap<tracing_core::Level, &'static Metadata<'static>>,
+}
+
+/// Callsites created by all `TracingDrain`s, since they are never freed
+///
+/// `tracing` needs Rust 1.65, so `Mutex::new` is `const` here.
+#[clippy::msrv = "1.65"]
+static CALLSITES: Mutex<Option<Callsites>> = Mutex::new(None);
+
+/// Run `f` on the callsites
+fn with_callsites<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut Callsites) -> R,
+{
+    f(CALLSITES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(Callsites::default))
+}
+
+/// Callsite of a record
+enum CallsiteFor {
+    /// With a field for the message and each key-value pair
+    Fields(&'static Metadata<'static>),
+    /// With the key-value pairs formatted in a single `kv` field
+    Overflow(&'static Metadata<'static>),
+}
+
+/// Find or create the callsite of a record
+///
+/// A new callsite is leaked and added under the lock, so that at most
+/// `max_callsites` are created, each once, but registered after releasing
+/// it: subscribers may log while registering callsites, maybe to a
+/// `TracingDrain`. Meanwhile, other threads may already send events with
+/// it, which subscribers only filter with `Subscriber::enabled`.
+fn callsite_for(
+    level: tracing_core::Level,
+    location: &crate::RecordLocation,
+    pairs: &[(Key, OwnedValue)],
+    max_callsites: usize,
+) -> CallsiteFor {
+    let key = CallsiteKey {
+        level,
+        module: location.module,
+        file: location.file,
+        line: location.line,
+        keys: pairs
+            .iter()
+            .map(|(key, _)| AsRef::<str>::as_ref(key).to_string())
+            .collect(),
+    };
+    let (found, created) = with_callsites(|callsites| {
+        if let Some(&metadata) = callsites.by_key.get(&key) {
+            return (CallsiteFor::Fields(metadata), None);
+        }
+        if callsites.by_key.len() >= max_callsites {
+            if let Some(&metadata) = callsites.overflow.get(&level) {
+                return (CallsiteFor::Overflow(metadata), None);
+            }
+            let location = crate::RecordLocation {
+                file: file!(),
+                line: line!(),
+                column: 0,
+                function: "",
+                module: module_path!(),
+            };
+            let (callsite, metadata) = leak_callsite(
+                "slog overflow event",
+                level,
+                &location,
+                &["message", "kv"],
+                Kind::EVENT,
+            );
+            callsites.overflow.insert(level, metadata);
+            return (CallsiteFor::Overflow(metadata), Some(callsite));
+        }
+        let fields: Vec<&str> = core::iter::once("message")
+            .chain(key.keys.iter().map(String::as_str))
+            .collect();
+        let name = Box::leak(
+            format!("event {}:{}", location.file, location.line)
+                .into_boxed_str(),
+        );
+        let (callsite, metadata) =
+            leak_callsite(name, level, location, &fields, Kind::EVENT);
+        callsites.by_key.insert(key, metadata);
+        (CallsiteFor::Fields(metadata), Some(callsite))
+    });
+    if let Some(callsite) = created {
+        callsite::register(callsite);
+    }
+    found
+}
+
+/// `Drain` sending records as `tracing` events
+///
+/// The message of a record becomes the `message` field of the event, and
+/// its key-value pairs, followed by the ones of its `Logger`, become the
+/// other fields. The module of a record becomes the `target` of the event.
+/// `Critical` records are logged at `ERROR`, and `Warning` ones at `WARN`.
+///
+/// `tracing` events need a callsite known to subscribers, so a callsite is
+/// created for each location, level and set of keys logged, and kept for
+/// the lifetime of the program. Callsites are shared by all `TracingDrain`s,
+/// and at most 1024 are created: once they are, records needing a new one
+/// are sent with a callsite of their level whose `target` is
+/// `slog::tracing_drain`, with their key-value pairs formatted in a single
+/// `kv` field. Keys that are only sometimes present, or vary between
+/// `Logger`s, count towards this limit.
+///
+/// An event has at most 32 fields: other key-value pairs, and ones with the
+/// same key as an earlier one, are not recorded.
+///
+/// ```
+/// use slog::{o, Logger, TracingDrain};
+///
+/// let log = Logger::root(TracingDrain::new(), o!("service" => "api"));
+/// slog::info!(log, "sent to tracing"; "count" => 3);
+/// ```
+///
+/// Requires the `tracing` feature.
+#[must_use = "does nothing by itself; needs to be attached to Logger"]
+#[derive(Debug, Clone, Copy)]
+pub struct TracingDrain {
+    /// Callsites after which records needing a new one overflow
+    max_callsites: usize,
+}
+
+impl TracingDrain {
+    /// Create `TracingDrain`
+    pub fn new() -> Self {
+        TracingDrain {
+            max_callsites: MAX_CALLSITES,
+        }
+    }
+}
+
+impl Default for TracingDrain {
+    fn default() -> Self {
+        TracingDrain::new()
+    }
+}
+
+impl Drain for TracingDrain {
+    type Ok = ();
+    type Err = Never;
+    fn log(
+        &self,
+        record: &Record<'_>,
+        values: &OwnedKVList,
+    ) -> result::Result<(), Never> {
+        let level = to_tracing_level(record.level());
+        if level > LevelFilter::current() {
+            return Ok(());
+        }
+        let mut pairs = Vec::new();
+        let mut serializer = CaptureSerializer::new(&mut pairs);
+        let _ = record.kv().serialize(record, &mut serializer);
+        let _ = values.serialize(record, &mut serializer);
+        // Keep the first pair of each key, moving them to the front
+        let mut kept = 0;
+        for i in 0..pairs.len() {
+            if kept == MAX_FIELDS - 1 {
+                break;
+            }
+            let key = AsRef::<str>::as_ref(&pairs[i].0);
+            let new = key != "message"
+                && !pairs[..kept]
+                    .iter()
+                    .any(|(k, _)| AsRef::<str>::as_ref(k) == key);
+            if new {
+                pairs.swap(kept, i);
+                kept += 1;
+            }
+        }
+        pairs.truncate(kept);
+        match callsite_for(level, record.location(), &pairs, self.max_callsites)
+        {
+            CallsiteFor::Fields(metadata) => {
+                let fields: Vec<Field> = metadata.fields().iter().collect();
+                let displays: Vec<_> = pairs
+                    .iter()
+                    .map(|(_, value)| field::display(value))
+                    .collect();
+                let mut values: [(&Field, Option<&dyn Value>); MAX_FIELDS] =
+                    [(&fields[0], None); MAX_FIELDS];
+                values[0].1 = Some(record.msg());
+                for (i, (_, value)) in pairs.iter().enumerate() {
+                    values[i + 1] =
+                        (&fields[i + 1], Some(as_value(value, &displays[i])));
+                }
+                dispatch(metadata, &metadata.fields().value_set(&values));
+            }
+            CallsiteFor::Overflow(metadata) => {
+                let kv = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let fields: Vec<Field> = metadata.fields().iter().collect();
+                let values: [(&Field, Option<&dyn Value>); 2] =
+                    [(&fields[0], Some(record.msg())), (&fields[1], Some(&kv))];
+                dispatch(metadata, &metadata.fields().value_set(&values));
+            }
+        }
+        Ok(())
+    }
+    #[inline]
+    fn is_enabled(&self, level: Level) -> bool {
+        to_tracing_level(level) <= LevelFilter::current()
+    }
+    #[inline]
+    fn flush(&self) -> result::Result<(), FlushError> {
+        Ok(())
+    }
+}
+
+fn dispatch(
+    metadata: &'static Metadata<'static>,
+    values: &field::ValueSet<'_>,
+) {
+    dispatcher::get_default(|dispatch| {
+        if dispatch.enabled(metadata) {
+            dispatch.event(&Event::new(metadata, values));
+        }
+    });
+}
+
+/// Record numbers, booleans and strings as such, and other values with
+/// their `Display` text
+fn as_value<'a>(
+    value: &'a OwnedValue,
+    display: &'a field::DisplayValue<&'a OwnedValue>,
+) -> &'a dyn Value {
+    match value {
+        OwnedValue::Usize(v) => v,
+        OwnedValue::Isize(v) => v,
+        OwnedValue::Bool(v) => v,
+        OwnedValue::U8(v) => v,
+        OwnedValue::I8(v) => v,
+        OwnedValue::U16(v) => v,
+        OwnedValue::I16(v) => v,
+        OwnedValue::U32(v) => v,
+        OwnedValue::I32(v) => v,
+        OwnedValue::F32(v) => v,
+        OwnedValue::U64(v) => v,
+        OwnedValue::I64(v) => v,
+        OwnedValue::F64(v) => v,
+        OwnedValue::U128(v) => v,
+        OwnedValue::I128(v) => v,
+        OwnedValue::Str(v) => v,
+        _ => display,
+    }
+}
+
+#[cfg(all(test, not(feature = "nothreads")))]
+mod tests {
+    use super::*;
+    use crate::testing::CaptureDrain;
+    use crate::{o, Logger, Serializer, TracingLayer};
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use tracing_core::{Dispatch, Subscriber};
+    use tracing_subscriber::layer::{Layer, SubscriberExt};
+
+    /// Logs to a `TracingDrain` when a callsite is registered, once armed
+    struct LogOnRegister(Logger, Arc<AtomicBool>);
+
+    impl<S: Subscriber> Layer<S> for LogOnRegister {
+        fn register_callsite(
+            &self,
+            metadata: &'static Metadata<'static>,
+        ) -> Interest {
+            if self.1.swap(false, Ordering::SeqCst) {
+                crate::info!(self.0, "registered"; "name" => metadata.name());
+            }
+            Interest::always()
+        }
+    }
+
+    #[test]
+    fn logs_while_registering_callsites() {
+        let log = Logger::root(TracingDrain::new(), o!());
+        let armed = Arc::new(AtomicBool::new(false));
+        let layer = LogOnRegister(log.clone(), armed.clone());
+        let dispatch =
+            Dispatch::new(tracing_subscriber::registry().with(layer));
+        dispatcher::with_default(&dispatch, || {
+            // Registers a new callsite, which logs again
+            armed.store(true, Ordering::SeqCst);
+            crate::info!(log, "first"; "reentrant-test" => true);
+        });
+        assert!(!armed.load(Ordering::SeqCst));
+    }
+
+    fn capture(capture: &CaptureDrain) -> Dispatch {
+        let layer = TracingLayer::new(Logger::root(capture.clone(), o!()));
+        Dispatch::new(tracing_subscriber::registry().with(layer))
+    }
+
+    #[test]
+    fn overflows_callsites() {
+        let captured = CaptureDrain::new();
+        let log = Logger::root(TracingDrain { max_callsites: 0 }, o!("x" => 1));
+        dispatcher::with_default(&capture(&captured), || {
+            crate::warn!(log, "overflow"; "overflow-test" => "a b", "n" => 2);
+        });
+
+        let records = captured.take();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].msg(), "overflow");
+        assert_eq!(records[0].level(), Level::Warning);
+        assert_eq!(records[0].module(), "slog::tracing_drain");
+        assert_eq!(
+            records[0].get("kv"),
+            Some(&OwnedValue::Str("n=2 overflow-test=a b x=1".to_string()))
+        );
+        assert_eq!(records[0].get("n"), None);
+    }
+
+    /// More key-value pairs than an event has fields, repeated once
+    struct ManyPairs;
+
+    impl KV for ManyPairs {
+        fn serialize(
+            &self,
+            _: &Record<'_>,
+            serializer: &mut dyn Serializer,
+        ) -> crate::Result {
+            for _ in 0..2 {
+                for i in 0..MAX_FIELDS {
+                    let key = Box::leak(format!("many{}", i).into_boxed_str());
+                    serializer.emit_usize(Key::from(&*key), i)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn truncates_fields() {
+        let captured = CaptureDrain::new();
+        let log = Logger::root(TracingDrain::new(), o!());
+        dispatcher::with_default(&capture(&captured), || {
+            crate::info!(log, "many"; "message" => "skipped", ManyPairs);
+        });
+
+        let records = captured.take();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].msg(), "many");
+        let keys: Vec<String> = records[0]
+            .kv()
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .collect();
+        let expected: Vec<String> =
+            (0..MAX_FIELDS - 1).map(|i| format!("many{}", i)).collect();
+        assert_eq!(keys, expected);
+    }
+}
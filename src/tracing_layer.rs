@@ -0,0 +1,314 @@
+//! Sending `tracing` events to slog
+//!
+//! See [`TracingLayer`].
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use std::error::Error as StdError;
+
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{self, Attributes, Id};
+use tracing_core::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::{
+    BorrowedKV, Drain, Key, Level, Logger, OwnedError, OwnedValue, Record,
+    RecordLocation, RecordStatic, Serializer, Value, KV,
+};
+
+fn from_tracing_level(level: tracing_core::Level) -> Level {
+    match level {
+        tracing_core::Level::ERROR => Level::Error,
+        tracing_core::Level::WARN => Level::Warning,
+        tracing_core::Level::INFO => Level::Info,
+        tracing_core::Level::DEBUG => Level::Debug,
+        tracing_core::Level::TRACE => Level::Trace,
+    }
+}
+
+/// `tracing_subscriber::Layer` sending `tracing` events to a `Logger`
+///
+/// Records are built at runtime: the `message` field of an event becomes
+/// the message of the record, and its `target` the module. The other
+/// fields of the event become key-value pairs, followed by the fields of
+/// the spans the event is in, from the innermost to the outermost one.
+/// `tracing` levels map to slog levels of the same name, with `WARN`
+/// becoming `Warning`.
+///
+/// Events disabled by the `Logger` are skipped, but the layer does not
+/// filter events or spans for other layers.
+///
+/// ```
+/// use slog::{o, Discard, Logger, TracingLayer};
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let log = Logger::root(Discard, o!());
+/// let _subscriber =
+///     tracing_subscriber::registry().with(TracingLayer::new(log));
+/// ```
+///
+/// Requires the `tracing` feature, and is not available with the
+/// `nothreads` feature, since layers must be `Send` and `Sync`.
+pub struct TracingLayer {
+    logger: Logger,
+}
+
+impl TracingLayer {
+    /// Create `TracingLayer` logging to `logger`
+    pub fn new(logger: Logger) -> Self {
+        TracingLayer { logger }
+    }
+}
+
+/// Fields recorded on a span, kept in its extensions
+struct SpanFields(Vec<(&'static str, OwnedValue)>);
+
+impl<S> Layer<S> for TracingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        attrs: &Attributes<'_>,
+        id: &Id,
+        ctx: Context<'_, S>,
+    ) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanFields(visitor.fields));
+    }
+
+    fn on_record(
+        &self,
+        id: &Id,
+        values: &span::Record<'_>,
+        ctx: Context<'_, S>,
+    ) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        let mut extensions = span.extensions_mut();
+        let fields = match extensions.get_mut::<SpanFields>() {
+            Some(fields) => fields,
+            None => return,
+        };
+        for (key, value) in visitor.fields {
+            match fields.0.iter_mut().find(|(k, _)| *k == key) {
+                Some(field) => field.1 = value,
+                None => fields.0.push((key, value)),
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = from_tracing_level(*metadata.level());
+        if !self.logger.is_enabled(level) {
+            return;
+        }
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut pairs = visitor.fields;
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope {
+                if let Some(fields) = span.extensions().get::<SpanFields>() {
+                    pairs.extend(fields.0.iter().cloned());
+                }
+            }
+        }
+        let location = RecordLocation {
+            file: metadata.file().unwrap_or(""),
+            line: metadata.line().unwrap_or(0),
+            column: 0,
+            function: "",
+            module: metadata.target(),
+        };
+        let rs = RecordStatic {
+            location: &location,
+            tag: "",
+            level,
+        };
+        let msg = visitor.message.unwrap_or_default();
+        self.logger.log(&Record::new(
+            &rs,
+            &format_args!("{}", msg),
+            BorrowedKV(&Pairs(&pairs)),
+        ));
+    }
+}
+
+/// Captures `tracing` fields, and the `message` one apart
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Vec<(&'static str, OwnedValue)>,
+}
+
+impl FieldVisitor {
+    fn push(&mut self, field: &Field, value: OwnedValue) {
+        if field.name() == "message" {
+            self.message = Some(value.to_string());
+        } else {
+            self.fields.push((field.name(), value));
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, OwnedValue::I64(value));
+    }
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, OwnedValue::U64(value));
+    }
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.push(field, OwnedValue::I128(value));
+    }
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.push(field, OwnedValue::U128(value));
+    }
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, OwnedValue::F64(value));
+    }
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, OwnedValue::Bool(value));
+    }
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, OwnedValue::Str(value.to_string()));
+    }
+    fn record_error(
+        &mut self,
+        field: &Field,
+        value: &(dyn StdError + 'static),
+    ) {
+        self.push(field, OwnedValue::Error(OwnedError::new(value)));
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, OwnedValue::Arguments(format!("{:?}", value)));
+    }
+}
+
+/// Captured fields, as key-value pairs
+struct Pairs<'a>(&'a [(&'static str, OwnedValue)]);
+
+impl KV for Pairs<'_> {
+    fn serialize(
+        &self,
+        record: &Record<'_>,
+        serializer: &mut dyn Serializer,
+    ) -> crate::Result {
+        for (key, value) in self.0 {
+            value.serialize(record, Key::from(*key), serializer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::CaptureDrain;
+    use crate::tracing_drain::new_callsite;
+    use crate::{o, TracingDrain};
+    use tracing_core::metadata::Kind;
+    use tracing_core::{dispatcher, Dispatch};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn dispatch(capture: &CaptureDrain) -> Dispatch {
+        let layer = TracingLayer::new(Logger::root(capture.clone(), o!()));
+        Dispatch::new(tracing_subscriber::registry().with(layer))
+    }
+
+    #[test]
+    fn round_trips_through_tracing() {
+        let capture = CaptureDrain::new();
+        let log = Logger::root(TracingDrain::new(), o!("service" => "api"));
+        dispatcher::with_default(&dispatch(&capture), || {
+            crate::crit!(log, "down {}", 5; "reason" => %"oom", "count" => 3);
+            crate::info!(log, "up"; "message" => "ignored", "ok" => true);
+        });
+
+        let records = capture.take();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].level(), Level::Error);
+        assert_eq!(records[0].msg(), "down 5");
+        assert_eq!(records[0].module(), "slog::tracing_layer::tests");
+        assert_eq!(
+            records[0].get("reason"),
+            Some(&OwnedValue::Arguments("oom".to_string()))
+        );
+        assert_eq!(records[0].get("count"), Some(&OwnedValue::I64(3)));
+        assert_eq!(
+            records[0].get("service"),
+            Some(&OwnedValue::Str("api".to_string()))
+        );
+        assert_eq!(records[1].msg(), "up");
+        assert_eq!(records[1].get("message"), None);
+        assert_eq!(records[1].get("ok"), Some(&OwnedValue::Bool(true)));
+    }
+
+    #[test]
+    fn includes_span_fields() {
+        let capture = CaptureDrain::new();
+        let dispatch = dispatch(&capture);
+        let location = RecordLocation {
+            file: file!(),
+            line: line!(),
+            column: 0,
+            function: "",
+            module: module_path!(),
+        };
+        let metadata = new_callsite(
+            "request",
+            tracing_core::Level::INFO,
+            &location,
+            &["request_id", "user"],
+            Kind::SPAN,
+        );
+        let fields: Vec<Field> = metadata.fields().iter().collect();
+        let log = Logger::root(TracingDrain::new(), o!());
+        dispatcher::with_default(&dispatch, || {
+            let values = [
+                (&fields[0], Some(&5u64 as &dyn tracing_core::field::Value)),
+                (&fields[1], None),
+            ];
+            let id = dispatch.new_span(&Attributes::new(
+                metadata,
+                &metadata.fields().value_set(&values),
+            ));
+            let values = [(
+                &fields[1],
+                Some(&"alice" as &dyn tracing_core::field::Value),
+            )];
+            dispatch.record(
+                &id,
+                &span::Record::new(&metadata.fields().value_set(&values)),
+            );
+            dispatch.enter(&id);
+            crate::warn!(log, "in span"; "request_id" => 6);
+            dispatch.exit(&id);
+            crate::warn!(log, "outside");
+            let _ = dispatch.try_close(id);
+        });
+
+        let records = capture.take();
+        assert_eq!(records.len(), 2);
+        let got: Vec<_> = records[0]
+            .kv()
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        assert_eq!(got, ["request_id=6", "request_id=5", "user=alice"]);
+        assert_eq!(records[1].get("request_id"), None);
+    }
+}